@@ -1,28 +1,42 @@
-use crate::damage::{DamageKind, Damage};
+use crate::damage::{DamageKind, Damage, DamageInstance};
 use crate::dice::*;
 use crate::space::Area;
 use crate::types::*;
 use crate::basetraits::*;
+use crate::modifier::*;
+use crate::citation::*;
 use crate::util;
+use crate::parse::{self, ParseError};
 
-use std::rc::Rc;
-use std::cmp::max;
+use alloc::sync::Arc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::max_by_key;
+use core::fmt;
+use core::str::FromStr;
 
 use rand::Rng;
 
 /// Represents a roll one would make to do damage of a certain kind (attacks can possess more than
 /// one damage roll--generally, one per kind of damage).
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DamageRoll(pub DiceExpr, pub DamageKind);
 
 impl DamageRoll {
-    fn roll<R: Rng>(&self, rng: &mut R) -> (Damage, DiceRoll) {
+    pub fn roll<R: Rng>(&self, rng: &mut R) -> (Damage, DiceRoll) {
         let dr = self.0.roll(rng);
         (
             Damage(util::clamp_isize(dr.value()), self.1),
             dr
         )
     }
+
+    /// This roll on a critical hit, with `extra` more of its first die (see
+    /// `DiceExpr::crit_with`).
+    pub fn crit(&self, extra: usize) -> DamageRoll {
+        DamageRoll(self.0.crit_with(extra), self.1)
+    }
 }
 
 impl ExpectedValue for DamageRoll {
@@ -34,7 +48,8 @@ impl ExpectedValue for DamageRoll {
 /// Expresses how many targets an action can affect. Exactly indicates that only the exact number
 /// can be targeted; Area indicates that an area is targeted. See also `AreaEffectDensity`. The
 /// default is Exactly(1).
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Target {
     Exactly(usize),
     Area(Area),
@@ -49,7 +64,8 @@ impl Default for Target {
 /// A saving throw DC versus an effect. The granting ability is implicit in most monsters, and
 /// requires some work to derive; for example, all dragons have Con-granted breath weapon DCs, and
 /// Cha-granted Frightful Presence DCs.
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SavingDC {
     Granted(Ability),
     Exactly(usize),
@@ -71,41 +87,128 @@ impl SavingDC {
 }
 
 /// The kind of saving throw for an effect.
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SaveKind {
     Ability(Ability),
     Death,
 }
 
+impl Cited for SaveKind {
+    const CITATION: Citation = Citation::phb(179);
+}
+
 impl SaveKind {
     pub fn modifier(&self, mods: &AMods) -> isize {
-        match self {
-            SaveKind::Ability(ab) => mods.0[*ab],
-            SaveKind::Death => 0,
+        self.modifier_stack(mods).total()
+    }
+
+    /// The modifiers to this saving throw, with their sources.
+    pub fn modifier_stack(&self, mods: &AMods) -> ModifierStack {
+        let mut stack = ModifierStack::default();
+        if let SaveKind::Ability(ab) = self {
+            stack.push(Modifier::new(ModifierSource::Ability(*ab), mods.0[*ab]));
         }
+        stack
     }
 }
 
+/// How an action-denial effect is valued as damage for CR purposes.
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ControlValue {
+    /// Worth exactly this much damage.
+    Damage(usize),
+    /// Worth the target's own expected damage per round, for this many rounds.
+    DeniedRounds(f64),
+}
+
 /// The effects that a successful save can have.
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SaveEffect {
     ReducesDamage(f64),
+    /// A failed save denies the target its actions (stunned, banished, etc.); a pass avoids it.
+    DeniesActions(ControlValue),
+}
+
+/// A condition an effect imposes (5e PHB, p. 290). Exhaustion, which has levels, isn't included.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Condition {
+    Blinded,
+    Charmed,
+    Deafened,
+    Frightened,
+    Grappled,
+    Incapacitated,
+    Invisible,
+    Paralyzed,
+    Petrified,
+    Poisoned,
+    Prone,
+    Restrained,
+    Stunned,
+    Unconscious,
+}
+
+impl Cited for Condition {
+    const CITATION: Citation = Citation::phb(290);
 }
 
 /// The actual description of a saving throw.
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Save(pub SaveKind, pub SavingDC, pub SaveEffect);
 
 /// How many uses the effect has in combat.
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Uses {
     Indefinite,
     PerDay(usize),
+    /// Usable again if a roll of the die at the start of the creature's turn is at least the
+    /// value (e.g. `Recharge(5, Die::d6())` is "Recharge 5–6"; 5e MM, p. 11).
     Recharge(Value, Die),
+    /// Usable again after a short or long rest.
+    ShortOrLongRest,
+    /// Usable this many times between short (or long) rests.
+    PerShortRest(usize),
+    /// Usable this many times between long rests.
+    PerLongRest(usize),
+    /// Spends charges, of which there are at most `max`, regaining `regain` each dawn (e.g. a
+    /// staff's 10 charges and 1d6+4; 5e DMG, p. 141).
+    Charges { max: usize, regain: DiceExpr },
+}
+
+impl fmt::Display for Uses {
+    /// As written in stat blocks; `Indefinite` is written as nothing.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Uses::Indefinite => Ok(()),
+            Uses::PerDay(n) => write!(f, "{}/Day", n),
+            Uses::Recharge(v, d) if *v >= d.sides() => write!(f, "Recharge {}", v),
+            Uses::Recharge(v, d) => write!(f, "Recharge {}\u{2013}{}", v, d.sides()),
+            Uses::ShortOrLongRest => write!(f, "Recharges after a Short or Long Rest"),
+            Uses::PerShortRest(n) => write!(f, "{}/Short Rest", n),
+            Uses::PerLongRest(n) => write!(f, "{}/Long Rest", n),
+            Uses::Charges { max, regain } => write!(f, "{} Charges, regains {} at dawn", max, regain),
+        }
+    }
+}
+
+impl FromStr for Uses {
+    type Err = ParseError;
+
+    /// Parse stat-block notation (see `parse::uses`).
+    fn from_str(s: &str) -> Result<Uses, ParseError> {
+        parse::complete(s, parse::uses)
+    }
 }
 
 /// Which kind of attack this is (controls which modifiers, if any, are selected).
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AttackKind {
     Melee,
     Ranged,
@@ -124,17 +227,27 @@ impl AttackKind {
 }
 
 /// The full description of an attack.
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Attack {
     pub kind: AttackKind,
     pub save: Option<Save>,
     pub target: Target,
     pub dmg_rolls: Vec<DamageRoll>,
     pub dmg_bonus: isize,  // Added to the first kind of damage in dmg_rolls
+    /// Kinds of damage the attacker may choose from for the first damage roll (e.g. Chromatic
+    /// Orb); empty if the kind is fixed.
+    pub dmg_kind_choices: Vec<DamageKind>,
     pub to_hit_bonus: isize,
+    /// More of the first damage roll's first die rolled on a critical hit (e.g. Brutal
+    /// Critical).
+    pub crit_extra_dice: usize,
     pub finesse: bool,
     pub proficient: bool,
     pub range: usize,
+    /// Conditions imposed on each target hit, or on each which fails the save if there is one.
+    /// These aren't valued as damage; see `SaveEffect::DeniesActions` for that.
+    pub conditions: Vec<Condition>,
 }
 
 impl Default for Attack {
@@ -145,10 +258,13 @@ impl Default for Attack {
             target: Default::default(),
             dmg_rolls: Vec::new(),
             dmg_bonus: 0,
+            dmg_kind_choices: Vec::new(),
             to_hit_bonus: 0,
+            crit_extra_dice: 0,
             finesse: false,
             proficient: false,
             range: 5,
+            conditions: Vec::new(),
         }
     }
 }
@@ -156,27 +272,105 @@ impl Default for Attack {
 impl Attack {
     /// The "to hit" modifier; see 5e PHB, p. 194
     pub fn modifier(&self, mods: &AMods, prof: ProfBonus) -> isize {
-        self.to_hit_bonus + if self.proficient { prof.0 } else { 0 } + match self.kind {
-            AttackKind::Special => 0,
-            ref k => if self.finesse {
-                max(AttackKind::Melee.modifier(mods), AttackKind::Ranged.modifier(mods))
-            } else {
-                k.modifier(mods)
-            }
+        self.modifier_stack(mods, prof).total()
+    }
+
+    /// Roll all of this attack's damage as a single instance, adding the damage bonus to the
+    /// first roll.
+    pub fn roll_damage<R: Rng>(&self, rng: &mut R) -> (DamageInstance, Vec<DiceRoll>) {
+        self.roll_rolls(&self.dmg_rolls, rng)
+    }
+
+    /// The damage rolls on a critical hit (5e PHB, p. 196), including `crit_extra_dice`.
+    pub fn crit_damage_rolls(&self) -> Vec<DamageRoll> {
+        self.dmg_rolls.iter().enumerate().map(|(idx, dr)| {
+            dr.crit(if idx == 0 { self.crit_extra_dice } else { 0 })
+        }).collect()
+    }
+
+    /// As `roll_damage`, for a critical hit.
+    pub fn roll_crit_damage<R: Rng>(&self, rng: &mut R) -> (DamageInstance, Vec<DiceRoll>) {
+        self.roll_rolls(&self.crit_damage_rolls(), rng)
+    }
+
+    fn roll_rolls<R: Rng>(&self, rolls: &[DamageRoll], rng: &mut R) -> (DamageInstance, Vec<DiceRoll>) {
+        rolls.iter().enumerate().map(|(idx, dr)| {
+            let roll = dr.0.roll(rng);
+            let bonus = if idx == 0 { self.dmg_bonus } else { 0 };
+            (Damage(util::clamp_isize(roll.value() + bonus), dr.1), roll)
+        }).fold((DamageInstance::default(), Vec::new()), |(mut inst, mut rolls), (dmg, roll)| {
+            inst.0.push(dmg);
+            rolls.push(roll);
+            (inst, rolls)
+        })
+    }
+
+    /// The modifiers to the attack roll, with their sources.
+    pub fn modifier_stack(&self, mods: &AMods, prof: ProfBonus) -> ModifierStack {
+        let mut stack = ModifierStack::default();
+        let ability = match self.kind {
+            AttackKind::Special => None,
+            AttackKind::Melee | AttackKind::Ranged if self.finesse => Some(
+                max_by_key(Ability::Str, Ability::Dex, |ab| mods.0[*ab])
+            ),
+            AttackKind::Melee => Some(Ability::Str),
+            AttackKind::Ranged => Some(Ability::Dex),
+        };
+        if let Some(ab) = ability {
+            stack.push(Modifier::new(ModifierSource::Ability(ab), mods.0[ab]));
         }
+        if self.proficient {
+            stack.push(Modifier::new(ModifierSource::Proficiency, prof.0));
+        }
+        if self.to_hit_bonus != 0 {
+            stack.push(Modifier::new(ModifierSource::Bonus, self.to_hit_bonus));
+        }
+        stack
+    }
+}
+
+impl FromStr for Attack {
+    type Err = ParseError;
+
+    /// Parse effect notation (see `parse::effect`).
+    fn from_str(s: &str) -> Result<Attack, ParseError> {
+        parse::complete(s, parse::effect)
     }
 }
 
 /// A kind of action that a creature can take.
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ActionKind {
-    Attack(Rc<Attack>),
-    Multiattack(Vec<Rc<Attack>>),
+    Attack(Arc<Attack>),
+    Multiattack(Vec<Arc<Attack>>),
+}
+
+impl ActionKind {
+    /// The attacks made by one use of this action.
+    pub fn attacks(&self) -> impl Iterator<Item = &Attack> {
+        match self {
+            ActionKind::Attack(atk) => core::slice::from_ref(atk),
+            ActionKind::Multiattack(atks) => atks.as_slice(),
+        }.iter().map(Arc::as_ref)
+    }
 }
 
 /// The full description of an action.
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Action {
     pub name: String,
     pub kind: ActionKind,
+    pub uses: Uses,
+}
+
+impl Action {
+    /// An action making a single attack, from effect notation which may also give its uses (see
+    /// `parse::action_effect`), as in
+    /// `"target: 3 nearest enemies; save DEX DC 15; on fail: 4d6 fire + prone; recharge 5-6"`.
+    pub fn parse(name: &str, notation: &str) -> Result<Action, ParseError> {
+        let (atk, uses) = parse::complete(notation, parse::action_effect)?;
+        Ok(Action { name: name.into(), kind: ActionKind::Attack(Arc::new(atk)), uses })
+    }
 }
@@ -0,0 +1,116 @@
+//! The armor of the 5e PHB (p. 145), and the AC it grants.
+
+use crate::basetraits::*;
+use crate::citation::*;
+
+/// The categories of armor, which determine how much of the Dex modifier applies (5e PHB, p. 144).
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum ArmorCategory {
+    Light,
+    /// Adds at most +2 from the Dex modifier.
+    Medium,
+    /// Doesn't add the Dex modifier.
+    Heavy,
+    /// Adds its AC to that of any other armor.
+    Shield,
+}
+
+impl ArmorCategory {
+    /// The greatest Dex modifier this category adds, or `None` if uncapped.
+    pub fn max_dex(self) -> Option<isize> {
+        match self {
+            ArmorCategory::Light | ArmorCategory::Shield => None,
+            ArmorCategory::Medium => Some(2),
+            ArmorCategory::Heavy => Some(0),
+        }
+    }
+}
+
+/// A kind of armor (5e PHB, p. 145).
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Armor {
+    pub name: &'static str,
+    pub category: ArmorCategory,
+    /// The AC before the Dex modifier, or the bonus for a shield.
+    pub base_ac: usize,
+    /// The Str score needed to move at full speed in it.
+    pub str_requirement: Option<isize>,
+    /// Whether the wearer has disadvantage on Dexterity (Stealth) checks.
+    pub stealth_disadvantage: bool,
+}
+
+impl Cited for Armor {
+    const CITATION: Citation = Citation::phb(145);
+}
+
+const fn armor(
+    name: &'static str, category: ArmorCategory, base_ac: usize,
+    str_requirement: Option<isize>, stealth_disadvantage: bool,
+) -> Armor {
+    Armor { name, category, base_ac, str_requirement, stealth_disadvantage }
+}
+
+/// The armor of the 5e PHB, p. 145, lightest first.
+pub const ARMORS: [Armor; 13] = {
+    use ArmorCategory::*;
+    [
+        armor("padded", Light, 11, None, true),
+        armor("leather", Light, 11, None, false),
+        armor("studded leather", Light, 12, None, false),
+        armor("hide", Medium, 12, None, false),
+        armor("chain shirt", Medium, 13, None, false),
+        armor("scale mail", Medium, 14, None, true),
+        armor("breastplate", Medium, 14, None, false),
+        armor("half plate", Medium, 15, None, true),
+        armor("ring mail", Heavy, 14, None, true),
+        armor("chain mail", Heavy, 16, Some(13), true),
+        armor("splint", Heavy, 17, Some(15), true),
+        armor("plate", Heavy, 18, Some(15), true),
+        armor("shield", Shield, 2, None, false),
+    ]
+};
+
+impl Armor {
+    /// Look up armor in `ARMORS` by name, ignoring case.
+    pub fn by_name(name: &str) -> Option<&'static Armor> {
+        ARMORS.iter().find(|a| a.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl ACKind {
+    /// The AC of wearing the given armor (or none), and perhaps a shield. A shield passed as
+    /// `armor` counts as wearing no armor with a shield.
+    pub fn from_armor(armor: Option<&Armor>, shield: bool) -> ACKind {
+        let shield_ac = if shield { 2 } else { 0 };
+        match armor {
+            None => if shield {
+                ACKind::ArmorDex(10 + shield_ac, None)
+            } else {
+                ACKind::Normal
+            },
+            Some(a) => match a.category {
+                ArmorCategory::Shield => ACKind::from_armor(None, true),
+                ArmorCategory::Heavy => ACKind::Armor(a.base_ac + shield_ac),
+                cat => ACKind::ArmorDex(a.base_ac + shield_ac, cat.max_dex()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn armor_class() {
+        let mods: AMods = (&AScores(Abilities { str: 10, dex: 18, con: 10, int: 10, wis: 10, cha: 10 })).into();
+        let ac = |name, shield| ACKind::from_armor(Armor::by_name(name), shield).armor_class(&mods);
+        assert_eq!(ac("studded leather", false), AC(16));
+        assert_eq!(ac("Half Plate", false), AC(17));
+        assert_eq!(ac("plate", true), AC(20));
+        assert_eq!(ac("shield", false), AC(16));
+        assert_eq!(ACKind::from_armor(None, false).armor_class(&mods), AC(14));
+        assert_eq!(ACKind::MageArmor.armor_class(&mods), AC(17));
+        assert_eq!(ACKind::ArmorDex(14, Some(2)).armor_class(&AMods::default()), AC(14));
+    }
+}
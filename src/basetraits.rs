@@ -1,11 +1,17 @@
-use std::ops::{Index, IndexMut};
-use std::borrow::Borrow;
+use core::ops::{Index, IndexMut};
+use core::borrow::Borrow;
+use core::fmt;
+use alloc::sync::Arc;
 
-use crate::dice::Die;
+use rand::Rng;
+
+use crate::dice::{Die, DiceExpr};
+use crate::citation::*;
 use crate::util;
 
 /// All six ability scores of 5e (5e PHB, p. 173)
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Abilities {
     pub str: isize,
     pub dex: isize,
@@ -17,6 +23,7 @@ pub struct Abilities {
 
 /// The six abilities themselves (5e PHB, p. 173)
 #[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Ability {
     Str, Dex, Con, Int, Wis, Cha,
 }
@@ -37,6 +44,10 @@ impl Abilities {
     }
 }
 
+impl Cited for Abilities {
+    const CITATION: Citation = Citation::phb(173);
+}
+
 impl Index<Ability> for Abilities {
     type Output = isize;
     
@@ -65,11 +76,53 @@ impl IndexMut<Ability> for Abilities {
     }
 }
 
+/// The skills, each governed by an ability (5e PHB, p. 174)
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Skill {
+    Athletics,
+    Acrobatics, SleightOfHand, Stealth,
+    Arcana, History, Investigation, Nature, Religion,
+    AnimalHandling, Insight, Medicine, Perception, Survival,
+    Deception, Intimidation, Performance, Persuasion,
+}
+
+impl Cited for Skill {
+    const CITATION: Citation = Citation::phb(174);
+}
+
+impl Skill {
+    pub const ALL: [Skill; 18] = [
+        Skill::Athletics,
+        Skill::Acrobatics, Skill::SleightOfHand, Skill::Stealth,
+        Skill::Arcana, Skill::History, Skill::Investigation, Skill::Nature, Skill::Religion,
+        Skill::AnimalHandling, Skill::Insight, Skill::Medicine, Skill::Perception, Skill::Survival,
+        Skill::Deception, Skill::Intimidation, Skill::Performance, Skill::Persuasion,
+    ];
+
+    /// The ability governing checks with this skill.
+    pub fn ability(self) -> Ability {
+        use Skill::*;
+        match self {
+            Athletics => Ability::Str,
+            Acrobatics | SleightOfHand | Stealth => Ability::Dex,
+            Arcana | History | Investigation | Nature | Religion => Ability::Int,
+            AnimalHandling | Insight | Medicine | Perception | Survival => Ability::Wis,
+            Deception | Intimidation | Performance | Persuasion => Ability::Cha,
+        }
+    }
+}
+
 /// Ability _scores_ (see 5e PHB, p. 173); just a wrapper around Abilities to avoid confusing
 /// units.
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AScores(pub Abilities);
 
+impl Cited for AScores {
+    const CITATION: Citation = Citation::phb(173);
+}
+
 impl Default for AScores {
     fn default() -> AScores {
         AScores(Abilities {
@@ -78,6 +131,136 @@ impl Default for AScores {
     }
 }
 
+/// Why a point-buy assignment is invalid.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum PointBuyError {
+    /// A score is outside 8 through 15.
+    OutOfRange(Ability, isize),
+    /// An ability is assigned more than once.
+    Duplicate(Ability),
+    /// The scores cost this many points, more than the 27 available.
+    OverBudget(usize),
+}
+
+impl fmt::Display for PointBuyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PointBuyError::OutOfRange(ab, s) => write!(f, "{:?} score {} is outside 8-15", ab, s),
+            PointBuyError::Duplicate(ab) => write!(f, "{:?} is assigned more than once", ab),
+            PointBuyError::OverBudget(cost) => write!(f, "scores cost {} points, over the budget of {}", cost, AScores::POINT_BUY_BUDGET),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PointBuyError {}
+
+/// What `AScores::apply_bonuses` does with a score which would exceed the cap.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum CapPolicy {
+    /// Fail with `ScoreCapError`.
+    Error,
+    /// Reduce the score to the cap.
+    Clamp,
+}
+
+/// An ability score would exceed its cap: the ability and the score it would have had.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct ScoreCapError(pub Ability, pub isize);
+
+impl fmt::Display for ScoreCapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} score {} exceeds its cap", self.0, self.1)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ScoreCapError {}
+
+impl AScores {
+    /// The greatest score a character can normally have (5e PHB, p. 173).
+    pub const CHARACTER_CAP: isize = 20;
+    /// The greatest score any creature can have (5e PHB, p. 173).
+    pub const MONSTER_CAP: isize = 30;
+
+    /// These scores with bonuses (e.g. racial increases) added, holding each score to `cap`
+    /// (usually `CHARACTER_CAP` or `MONSTER_CAP`) per the policy. Scores already over the cap
+    /// aren't checked unless they receive a bonus.
+    pub fn apply_bonuses(&self, bonuses: &[(Ability, isize)], cap: isize, policy: CapPolicy) -> Result<AScores, ScoreCapError> {
+        let mut out = self.clone();
+        for (ab, bonus) in bonuses {
+            let score = out.0[*ab] + bonus;
+            out.0[*ab] = if score <= cap {
+                score
+            } else {
+                match policy {
+                    CapPolicy::Error => return Err(ScoreCapError(*ab, score)),
+                    CapPolicy::Clamp => cap,
+                }
+            };
+        }
+        Ok(out)
+    }
+
+    /// The points available for point buy (5e PHB, p. 13).
+    pub const POINT_BUY_BUDGET: usize = 27;
+
+    /// The point cost of a score, if it can be bought (5e PHB, p. 13).
+    pub fn point_buy_cost(score: isize) -> Option<usize> {
+        match score {
+            8..=13 => Some((score - 8) as usize),
+            14 => Some(7),
+            15 => Some(9),
+            _ => None,
+        }
+    }
+
+    /// Scores from the point-buy variant (5e PHB, p. 13): each listed ability gets its score, and
+    /// the rest get 8. Scores must be 8 through 15 and cost at most 27 points in total.
+    pub fn point_buy(scores: &[(Ability, isize)]) -> Result<AScores, PointBuyError> {
+        let mut out = AScores(Abilities { str: 8, dex: 8, con: 8, int: 8, wis: 8, cha: 8 });
+        let mut cost = 0;
+        for (idx, (ab, score)) in scores.iter().enumerate() {
+            if scores[..idx].iter().any(|(prev, _)| prev == ab) {
+                return Err(PointBuyError::Duplicate(*ab));
+            }
+            cost += AScores::point_buy_cost(*score).ok_or(PointBuyError::OutOfRange(*ab, *score))?;
+            out.0[*ab] = *score;
+        }
+        if cost > AScores::POINT_BUY_BUDGET {
+            return Err(PointBuyError::OverBudget(cost));
+        }
+        Ok(out)
+    }
+
+    /// The standard set of scores (5e PHB, p. 13).
+    pub const STANDARD_ARRAY: [isize; 6] = [15, 14, 13, 12, 10, 8];
+
+    /// Scores from the standard array, assigned from highest to lowest in the given order of
+    /// abilities. Abilities given more than once keep their last assignment, and any left out
+    /// keep 10.
+    pub fn standard_array(order: &[Ability; 6]) -> AScores {
+        let mut out = AScores::default();
+        for (ab, score) in order.iter().zip(AScores::STANDARD_ARRAY.iter()) {
+            out.0[*ab] = *score;
+        }
+        out
+    }
+
+    /// Roll scores in order, each the highest three of 4d6 (5e PHB, p. 13).
+    pub fn roll_standard<R: Rng>(rng: &mut R) -> AScores {
+        let ex = DiceExpr::KeepHighest(4, 3, Arc::new(DiceExpr::Die(Die::d6())));
+        AScores(Abilities {
+            str: ex.roll_value(rng),
+            dex: ex.roll_value(rng),
+            con: ex.roll_value(rng),
+            int: ex.roll_value(rng),
+            wis: ex.roll_value(rng),
+            cha: ex.roll_value(rng),
+        })
+    }
+}
+
 /// Ability _modifiers_ (see 5e PHB, p. 173); just a wrapper around Abilities to avoid confusing
 /// units.
 #[derive(Debug,Clone)]
@@ -89,6 +272,10 @@ impl<T> From<T> for AMods where T: Borrow<AScores> {
     }
 }
 
+impl Cited for AMods {
+    const CITATION: Citation = Citation::phb(173);
+}
+
 impl Default for AMods {
     fn default() -> AMods {
         (&AScores::default()).into()
@@ -97,26 +284,154 @@ impl Default for AMods {
 
 /// Creature size (5e PHB, p. 191)
 #[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Size {
     Tiny, Small, Medium, Large, Huge, Gargantuan
 }
 
+impl Cited for Size {
+    const CITATION: Citation = Citation::phb(191);
+}
+
 impl Size {
     /// 5e DMG, p. 276
     pub fn hit_die(&self) -> Die {
         match self {
-            Size::Tiny => Die(4),
-            Size::Small => Die(6),
-            Size::Medium => Die(8),
-            Size::Large => Die(10),
-            Size::Huge => Die(12),
-            Size::Gargantuan => Die(20),
+            Size::Tiny => Die::d4(),
+            Size::Small => Die::d6(),
+            Size::Medium => Die::d8(),
+            Size::Large => Die::d10(),
+            Size::Huge => Die::d12(),
+            Size::Gargantuan => Die::d20(),
+        }
+    }
+
+    /// The side of the square a creature controls in combat, in feet (5e PHB, p. 191).
+    /// Gargantuan creatures control 20 feet or more; this is the least.
+    pub fn space_ft(&self) -> f64 {
+        match self {
+            Size::Tiny => 2.5,
+            Size::Small | Size::Medium => 5.0,
+            Size::Large => 10.0,
+            Size::Huge => 15.0,
+            Size::Gargantuan => 20.0,
+        }
+    }
+
+    /// The usual reach of a creature's melee attacks, in feet; individual attacks may differ. This
+    /// follows the stat blocks of the 5e MM rather than a stated rule.
+    pub fn reach_ft(&self) -> usize {
+        match self {
+            Size::Tiny | Size::Small | Size::Medium | Size::Large => 5,
+            Size::Huge => 10,
+            Size::Gargantuan => 15,
+        }
+    }
+
+    /// The multiplier to carrying capacity, and to how much a creature can push, drag, or lift
+    /// (5e PHB, p. 176).
+    pub fn carrying_multiplier(&self) -> f64 {
+        match self {
+            Size::Tiny => 0.5,
+            Size::Small | Size::Medium => 1.0,
+            Size::Large => 2.0,
+            Size::Huge => 4.0,
+            Size::Gargantuan => 8.0,
+        }
+    }
+}
+
+/// Movement speeds in feet per round, by mode (5e PHB, p. 190); zero for modes a creature lacks.
+/// The default is a 30-foot walking speed.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Speeds {
+    pub walk: usize,
+    pub fly: usize,
+    pub swim: usize,
+    pub climb: usize,
+    pub burrow: usize,
+    /// Whether the creature can hover, and so doesn't fall when knocked prone while flying.
+    pub hover: bool,
+}
+
+impl Cited for Speeds {
+    const CITATION: Citation = Citation::phb(190);
+}
+
+impl Default for Speeds {
+    fn default() -> Speeds {
+        Speeds { walk: 30, fly: 0, swim: 0, climb: 0, burrow: 0, hover: false }
+    }
+}
+
+/// Creature type (5e MM, p. 6). Subtypes, such as "goblinoid" or "shapechanger", are free-form
+/// tags kept alongside.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CreatureType {
+    Aberration, Beast, Celestial, Construct, Dragon, Elemental, Fey, Fiend, Giant, Humanoid,
+    Monstrosity, Ooze, Plant, Undead,
+}
+
+impl Cited for CreatureType {
+    const CITATION: Citation = Citation::mm(6);
+}
+
+/// The law-chaos axis of alignment (5e PHB, p. 122).
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LawChaos {
+    Lawful, Neutral, Chaotic,
+}
+
+/// The good-evil axis of alignment (5e PHB, p. 122).
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GoodEvil {
+    Good, Neutral, Evil,
+}
+
+/// Alignment (5e PHB, p. 122). Creatures without the capacity for moral choice are Unaligned
+/// (the default); Any stands for stat blocks of "any alignment".
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Alignment {
+    Aligned(LawChaos, GoodEvil),
+    #[default]
+    Unaligned,
+    Any,
+}
+
+impl Cited for Alignment {
+    const CITATION: Citation = Citation::phb(122);
+}
+
+impl fmt::Display for Alignment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Alignment::Aligned(LawChaos::Neutral, GoodEvil::Neutral) => write!(f, "neutral"),
+            Alignment::Aligned(lc, ge) => write!(f, "{} {}",
+                match lc {
+                    LawChaos::Lawful => "lawful",
+                    LawChaos::Neutral => "neutral",
+                    LawChaos::Chaotic => "chaotic",
+                },
+                match ge {
+                    GoodEvil::Good => "good",
+                    GoodEvil::Neutral => "neutral",
+                    GoodEvil::Evil => "evil",
+                },
+            ),
+            Alignment::Unaligned => write!(f, "unaligned"),
+            Alignment::Any => write!(f, "any alignment"),
         }
     }
 }
 
 /// Challenge rating (5e DMG, p. 82 and others)
 #[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CR {
     CR0, CROneEighth, CROneQuarter, CROneHalf,
     CR1, CR2, CR3, CR4, CR5, CR6, CR7, CR8, CR9, CR10,
@@ -124,10 +439,14 @@ pub enum CR {
     CR21, CR22, CR23, CR24, CR25, CR26, CR27, CR28, CR29, CR30,
 }
 
-impl Into<f64> for CR {
-    fn into(self) -> f64 {
+impl Cited for CR {
+    const CITATION: Citation = Citation::dmg(82);
+}
+
+impl From<CR> for f64 {
+    fn from(cr: CR) -> f64 {
         use CR::*;
-        match self {
+        match cr {
             CR0 => 0.0, CROneEighth => 0.125, CROneQuarter => 0.25, CROneHalf => 0.5,
             CR1 => 1.0, CR2 => 2.0, CR3 => 3.0, CR4 => 4.0, CR5 => 5.0,
             CR6 => 6.0, CR7 => 7.0, CR8 => 8.0, CR9 => 9.0, CR10 => 10.0,
@@ -185,14 +504,75 @@ impl From<f64> for CR {
 #[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
 pub struct ProfBonus(pub isize);
 
+impl Cited for ProfBonus {
+    const CITATION: Citation = Citation::phb(12);
+}
+
+/// How much of the proficiency bonus applies to a roll.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash,Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProficiencyLevel {
+    #[default]
+    None,
+    /// Half the bonus, rounded down (e.g. Jack of All Trades, 5e PHB, p. 54).
+    Half,
+    Proficient,
+    /// Double the bonus (5e PHB, p. 96).
+    Expertise,
+}
+
+impl ProficiencyLevel {
+    /// The part of the proficiency bonus which applies.
+    pub fn bonus(self, prof: ProfBonus) -> isize {
+        match self {
+            ProficiencyLevel::None => 0,
+            ProficiencyLevel::Half => prof.0 / 2,
+            ProficiencyLevel::Proficient => prof.0,
+            ProficiencyLevel::Expertise => 2 * prof.0,
+        }
+    }
+}
+
 /// Hit points (5e PHB, p. 12)
 #[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
 pub struct HP(pub usize);
 
+impl Cited for HP {
+    const CITATION: Citation = Citation::phb(12);
+}
+
+/// Experience points (5e PHB, p. 15)
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
+pub struct XP(pub usize);
+
+impl Cited for XP {
+    const CITATION: Citation = Citation::phb(15);
+}
+
+/// 5e DMG, p. 275 (CR 0 is 10 XP, or 0 for creatures without effective attacks)
+impl From<CR> for XP {
+    fn from(cr: CR) -> XP {
+        use CR::*;
+        XP(match cr {
+            CR0 => 10, CROneEighth => 25, CROneQuarter => 50, CROneHalf => 100,
+            CR1 => 200, CR2 => 450, CR3 => 700, CR4 => 1_100, CR5 => 1_800,
+            CR6 => 2_300, CR7 => 2_900, CR8 => 3_900, CR9 => 5_000, CR10 => 5_900,
+            CR11 => 7_200, CR12 => 8_400, CR13 => 10_000, CR14 => 11_500, CR15 => 13_000,
+            CR16 => 15_000, CR17 => 18_000, CR18 => 20_000, CR19 => 22_000, CR20 => 25_000,
+            CR21 => 33_000, CR22 => 41_000, CR23 => 50_000, CR24 => 62_000, CR25 => 75_000,
+            CR26 => 90_000, CR27 => 105_000, CR28 => 120_000, CR29 => 135_000, CR30 => 155_000,
+        })
+    }
+}
+
 /// Armor class (5e PHB, p. 14)
 #[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
 pub struct AC(pub usize);
 
+impl Cited for AC {
+    const CITATION: Citation = Citation::phb(14);
+}
+
 /// 5e DMG, p. 274
 impl From<CR> for AC {
     fn from(cr: CR) -> AC {
@@ -270,8 +650,39 @@ impl From<HP> for CR {
 }
 
 impl CR {
+    /// Every challenge rating, in ascending order.
+    pub const ALL: [CR; 34] = {
+        use CR::*;
+        [
+            CR0, CROneEighth, CROneQuarter, CROneHalf,
+            CR1, CR2, CR3, CR4, CR5, CR6, CR7, CR8, CR9, CR10,
+            CR11, CR12, CR13, CR14, CR15, CR16, CR17, CR18, CR19, CR20,
+            CR21, CR22, CR23, CR24, CR25, CR26, CR27, CR28, CR29, CR30,
+        ]
+    };
+
+    /// All challenge ratings, in ascending order.
+    pub fn iter() -> impl Iterator<Item = CR> {
+        CR::ALL.iter().cloned()
+    }
+
+    fn index(self) -> usize {
+        CR::ALL.iter().position(|c| *c == self).unwrap_or(0)
+    }
+
+    /// The rating `n` steps higher (counting the fractional ratings as steps), or CR 30 if there
+    /// are none so high.
+    pub fn step_up(self, n: usize) -> CR {
+        CR::ALL[self.index().saturating_add(n).min(CR::ALL.len() - 1)]
+    }
+
+    /// The rating `n` steps lower, or CR 0 if there are none so low.
+    pub fn step_down(self, n: usize) -> CR {
+        CR::ALL[self.index().saturating_sub(n)]
+    }
+
     /// 5e DMG, p. 274
-    fn for_expected_damage(dmg: usize) -> CR {
+    pub fn for_expected_damage(dmg: usize) -> CR {
         use CR::*;
         match dmg {
             x if x <= 1 => CR0,
@@ -311,7 +722,16 @@ impl CR {
         }
     }
 
-    /// 5e PHB, p. 274; the "to hit bonus" is across any attack with any modifier (Str plus
+    /// The middle of the damage per round range of this rating (5e DMG, p. 274), as the inverse
+    /// of `for_expected_damage`.
+    pub fn expected_damage(&self) -> usize {
+        let mut range = (0..=320).filter(|&d| CR::for_expected_damage(d) == *self);
+        let lo = range.next().unwrap_or(0);
+        let hi = range.next_back().unwrap_or(lo);
+        (lo + hi) / 2
+    }
+
+    /// 5e DMG, p. 274; the "to hit bonus" is across any attack with any modifier (Str plus
     /// presumed proficiency for melee, Dex plus prof for ranged, Granting mod for special, etc.)
     pub fn to_hit_bonus(&self) -> isize {
         let crf: f64 = (*self).into();
@@ -331,7 +751,7 @@ impl CR {
         }
     }
 
-    /// 5e PHB, p. 274; this is save DCs specifically within attacks.
+    /// 5e DMG, p. 274; this is save DCs specifically within attacks.
     pub fn save_dc(&self) -> isize {
         let crf: f64 = (*self).into();
         match crf {
@@ -352,12 +772,17 @@ impl CR {
 
 /// (source TODO! Scraped from 5e MM)
 #[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ACKind {
     Normal,
     UnarmoredDefense,
     Armor(usize),
-    ArmorDex(usize),
+    /// Armor adding the Dex modifier, capped at the given maximum if any (e.g. +2 for medium
+    /// armor).
+    ArmorDex(usize, Option<isize>),
     Natural(usize),
+    /// 13 plus the Dex modifier, without armor (Mage Armor, 5e PHB, p. 256).
+    MageArmor,
 }
 
 impl ACKind {
@@ -365,8 +790,81 @@ impl ACKind {
         AC(match self {
             ACKind::Normal => util::clamp_isize(10 + mods.0.dex),
             ACKind::UnarmoredDefense => util::clamp_isize(10 + mods.0.dex + mods.0.con),
+            ACKind::MageArmor => util::clamp_isize(13 + mods.0.dex),
             ACKind::Armor(x) | ACKind::Natural(x) => *x,
-            ACKind::ArmorDex(x) => util::clamp_isize((*x as isize) + mods.0.dex),
+            ACKind::ArmorDex(x, cap) => util::clamp_isize(
+                (*x as isize) + cap.map_or(mods.0.dex, |c| mods.0.dex.min(c))
+            ),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_buy() {
+        use Ability::*;
+        let scores = AScores::point_buy(&[(Str, 15), (Dex, 14), (Con, 13), (Wis, 12), (Cha, 10)]).unwrap();
+        assert_eq!((scores.0.str, scores.0.int), (15, 8));
+        assert_eq!(AScores::point_buy(&[(Str, 16)]).unwrap_err(), PointBuyError::OutOfRange(Str, 16));
+        assert_eq!(AScores::point_buy(&[(Str, 15), (Str, 15)]).unwrap_err(), PointBuyError::Duplicate(Str));
+        assert!(AScores::point_buy(&[(Str, 15), (Dex, 15), (Con, 15)]).is_ok());
+        assert_eq!(
+            AScores::point_buy(&[(Str, 15), (Dex, 15), (Con, 15), (Int, 9)]).unwrap_err(),
+            PointBuyError::OverBudget(28),
+        );
+    }
+
+    #[test]
+    fn alignment() {
+        let lg = Alignment::Aligned(LawChaos::Lawful, GoodEvil::Good);
+        assert_eq!(format!("{}", lg), "lawful good");
+        assert_eq!(format!("{}", Alignment::Aligned(LawChaos::Neutral, GoodEvil::Neutral)), "neutral");
+        assert_eq!(format!("{}", Alignment::default()), "unaligned");
+    }
+
+    #[test]
+    fn cr_steps() {
+        assert_eq!(CR::CROneHalf.step_up(1), CR::CR1);
+        assert_eq!(CR::CR1.step_down(2), CR::CROneQuarter);
+        assert_eq!(CR::CR29.step_up(5), CR::CR30);
+        assert_eq!(CR::CROneEighth.step_down(5), CR::CR0);
+        assert_eq!(CR::iter().count(), 34);
+        assert_eq!((CR::CR1.expected_damage(), CR::CR20.expected_damage()), (11, 131));
+        assert!(CR::iter().all(|cr| CR::for_expected_damage(cr.expected_damage()) == cr));
+    }
+
+    #[test]
+    fn xp() {
+        assert_eq!(XP::from(CR::CROneEighth), XP(25));
+        assert_eq!(XP::from(CR::CR30), XP(155_000));
+        assert!(CR::ALL.windows(2).all(|w| XP::from(w[0]) < XP::from(w[1])));
+    }
+
+    #[test]
+    fn bonuses() {
+        use Ability::*;
+        let base = AScores::standard_array(&[Str, Dex, Con, Int, Wis, Cha]);
+        let raised = base.apply_bonuses(&[(Str, 2), (Cha, 1)], AScores::CHARACTER_CAP, CapPolicy::Error).unwrap();
+        assert_eq!((raised.0.str, raised.0.cha), (17, 9));
+        let over = &[(Str, 2), (Str, 4)];
+        assert_eq!(
+            base.apply_bonuses(over, AScores::CHARACTER_CAP, CapPolicy::Error).unwrap_err(),
+            ScoreCapError(Str, 21),
+        );
+        assert_eq!(base.apply_bonuses(over, AScores::CHARACTER_CAP, CapPolicy::Clamp).unwrap().0.str, 20);
+        assert_eq!(base.apply_bonuses(over, AScores::MONSTER_CAP, CapPolicy::Error).unwrap().0.str, 21);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn generation() {
+        use Ability::*;
+        let scores = AScores::standard_array(&[Dex, Con, Wis, Str, Cha, Int]);
+        assert_eq!((scores.0.dex, scores.0.con, scores.0.int), (15, 14, 8));
+        let rolled = AScores::roll_standard(&mut rand::thread_rng());
+        assert!((3..=18).contains(&rolled.0.cha));
+    }
+}
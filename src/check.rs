@@ -0,0 +1,215 @@
+//! d20 tests: attack rolls, ability checks, and saving throws (5e PHB, p. 7).
+
+use crate::dice::*;
+
+use alloc::sync::Arc;
+
+use rand::Rng;
+
+/// The kinds of d20 test, which differ in how natural 1s and 20s are treated.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum D20TestKind {
+    /// A natural 20 always hits (and is a critical hit), and a natural 1 always misses (5e PHB,
+    /// p. 194).
+    AttackRoll,
+    AbilityCheck,
+    SavingThrow,
+}
+
+/// Advantage and disadvantage on a d20 test (5e PHB, p. 173).
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub enum Advantage {
+    #[default]
+    None,
+    Advantage,
+    Disadvantage,
+    /// Advantage rolling three dice instead of two (Elven Accuracy, 5e XGE, p. 74).
+    ElvenAccuracy,
+}
+
+impl Advantage {
+    /// The die rolled for a test with this advantage, given the die rolled normally.
+    pub fn apply(self, die: DiceExpr) -> DiceExpr {
+        match self {
+            Advantage::None => die,
+            Advantage::Advantage => DiceExpr::KeepHighest(2, 1, Arc::new(die)),
+            Advantage::Disadvantage => DiceExpr::KeepLowest(2, 1, Arc::new(die)),
+            Advantage::ElvenAccuracy => DiceExpr::KeepHighest(3, 1, Arc::new(die)),
+        }
+    }
+
+    /// The state with both this and another source of advantage or disadvantage: any of each
+    /// cancel out (5e PHB, p. 173), and Elven Accuracy is kept over plain advantage.
+    pub fn combine(self, other: Advantage) -> Advantage {
+        match (self, other) {
+            (Advantage::None, x) | (x, Advantage::None) => x,
+            (Advantage::Disadvantage, Advantage::Disadvantage) => Advantage::Disadvantage,
+            (Advantage::Disadvantage, _) | (_, Advantage::Disadvantage) => Advantage::None,
+            (Advantage::ElvenAccuracy, _) | (_, Advantage::ElvenAccuracy) => Advantage::ElvenAccuracy,
+            (Advantage::Advantage, Advantage::Advantage) => Advantage::Advantage,
+        }
+    }
+
+    /// The adjustment to a passive check with this advantage (5e PHB, p. 175).
+    pub fn passive_bonus(self) -> isize {
+        match self {
+            Advantage::None => 0,
+            Advantage::Advantage | Advantage::ElvenAccuracy => 5,
+            Advantage::Disadvantage => -5,
+        }
+    }
+}
+
+/// A d20 test: a roll of `die` (e.g. a d20, or two for advantage) plus `modifier`, succeeding if
+/// the total meets or beats `target` (a DC or AC). The die's faces are taken to be natural rolls.
+#[derive(Debug,Clone)]
+pub struct D20Test {
+    pub kind: D20TestKind,
+    pub die: DiceExpr,
+    pub modifier: isize,
+    pub target: isize,
+}
+
+impl D20Test {
+    pub fn new(kind: D20TestKind, die: DiceExpr, modifier: isize, target: isize) -> D20Test {
+        D20Test { kind, die, modifier, target }
+    }
+
+    /// The same test rolled with advantage or disadvantage.
+    pub fn with_advantage(mut self, adv: Advantage) -> D20Test {
+        self.die = adv.apply(self.die);
+        self
+    }
+
+    /// The probability of success, including automatic hits and misses on attack rolls.
+    pub fn p_success(&self) -> f64 {
+        match self.kind {
+            D20TestKind::AttackRoll => self.die.distribution().0.iter().filter(|(v, _)| match v {
+                20 => true,
+                1 => false,
+                v => v + self.modifier >= self.target,
+            }).map(|(_, p)| p).sum(),
+            _ => self.die.prob_pass(self.target - self.modifier),
+        }
+    }
+
+    /// The probability of a critical hit (a natural 20 on an attack roll; 5e PHB, p. 196).
+    pub fn p_crit(&self) -> f64 {
+        match self.kind {
+            D20TestKind::AttackRoll => self.die.prob_pass(20),
+            _ => 0.0,
+        }
+    }
+
+    /// The probability of an automatic miss (a natural 1 on an attack roll).
+    pub fn p_fumble(&self) -> f64 {
+        match self.kind {
+            D20TestKind::AttackRoll => self.die.cum_prob(1),
+            _ => 0.0,
+        }
+    }
+}
+
+/// The probability that a roll of `a` plus `a_mod` is strictly greater than a roll of `b` plus
+/// `b_mod`, as when the initiator of a contest must beat the other side (a tie leaves the
+/// situation as it was; 5e PHB, p. 174).
+pub fn p_exceeds(a: &DiceExpr, a_mod: isize, b: &DiceExpr, b_mod: isize) -> f64 {
+    a.distribution().0.iter().map(|(va, pa)| pa * b.cum_prob(va + a_mod - b_mod - 1)).sum()
+}
+
+/// The chance that the initiator of a contest between plain d20 checks succeeds (see
+/// `p_exceeds`); ties leave the situation as it was, so count as failures.
+pub fn contest(initiator_mod: isize, defender_mod: isize) -> f64 {
+    let d20 = DiceExpr::Die(Die::d20());
+    p_exceeds(&d20, initiator_mod, &d20, defender_mod)
+}
+
+/// Roll a contest, returning whether the initiator's total (`initiator` plus `initiator_mod`)
+/// strictly beats the defender's.
+pub fn roll_contest<R: Rng>(
+    initiator: &DiceExpr, initiator_mod: isize, defender: &DiceExpr, defender_mod: isize, rng: &mut R,
+) -> bool {
+    initiator.roll_value(rng) + initiator_mod > defender.roll_value(rng) + defender_mod
+}
+
+/// The distribution of the number of successes among independent tests with the given chances
+/// of success (e.g. the hits of a multiattack, from each attack's `D20Test::p_success`).
+pub fn success_count(ps: &[f64]) -> Distribution {
+    let mut counts = vec![1.0];
+    for p in ps {
+        let mut next = vec![0.0; counts.len() + 1];
+        for (k, c) in counts.iter().enumerate() {
+            next[k] += c * (1.0 - p);
+            next[k + 1] += c * p;
+        }
+        counts = next;
+    }
+    Distribution(counts.into_iter().enumerate().map(|(k, p)| (k as Value, p)).collect())
+}
+
+/// The probability that at least one of the tests succeeds.
+pub fn p_any_success(ps: &[f64]) -> f64 {
+    1.0 - ps.iter().map(|p| 1.0 - p).product::<f64>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_rolls() {
+        let d20 = DiceExpr::Die(Die::d20());
+        // +5 against AC 30 hits only on a natural 20; against AC 2, misses only on a natural 1.
+        let hard = D20Test::new(D20TestKind::AttackRoll, d20.clone(), 5, 30);
+        assert!((hard.p_success() - 0.05).abs() < 1e-9);
+        let easy = D20Test::new(D20TestKind::AttackRoll, d20.clone(), 5, 2);
+        assert!((easy.p_success() - 0.95).abs() < 1e-9);
+        assert!((easy.p_crit() - 0.05).abs() < 1e-9);
+        assert!((easy.p_fumble() - 0.05).abs() < 1e-9);
+        let save = D20Test::new(D20TestKind::SavingThrow, d20, 5, 30);
+        assert_eq!(save.p_success(), 0.0);
+        assert_eq!(save.p_crit(), 0.0);
+    }
+
+    #[test]
+    fn combining() {
+        use Advantage as A;
+        assert_eq!(A::Advantage.combine(A::Disadvantage), A::None);
+        assert_eq!(A::ElvenAccuracy.combine(A::Advantage), A::ElvenAccuracy);
+        assert_eq!(A::Disadvantage.combine(A::ElvenAccuracy), A::None);
+        assert_eq!(A::None.combine(A::Disadvantage), A::Disadvantage);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn contests() {
+        let d20 = DiceExpr::Die(Die::d20());
+        assert!((p_exceeds(&d20, 0, &d20, 0) - 0.475).abs() < 1e-9);
+        assert_eq!(p_exceeds(&d20, 0, &d20, 20), 0.0);
+        assert!((contest(5, 0) - p_exceeds(&d20, 5, &d20, 0)).abs() < 1e-9);
+        let mut rng = rand::thread_rng();
+        assert!(roll_contest(&d20, 20, &d20, 0, &mut rng));
+        assert!(!roll_contest(&d20, 0, &d20, 19, &mut rng));
+    }
+
+    #[test]
+    fn counts() {
+        let ps = [0.5, 0.5, 0.25];
+        let dist = success_count(&ps);
+        assert!((dist.0[0].1 - 0.1875).abs() < 1e-9);
+        assert!((dist.0[3].1 - 0.0625).abs() < 1e-9);
+        assert!((p_any_success(&ps) - (1.0 - dist.cum_prob(0))).abs() < 1e-9);
+    }
+
+    #[test]
+    fn advantage() {
+        let test = D20Test::new(D20TestKind::AttackRoll, DiceExpr::Die(Die::d20()), 0, 11);
+        let adv = test.clone().with_advantage(Advantage::Advantage);
+        assert!((adv.p_success() - 0.75).abs() < 1e-9);
+        assert!((adv.p_crit() - (1.0 - 0.95 * 0.95)).abs() < 1e-9);
+        let dis = test.clone().with_advantage(Advantage::Disadvantage);
+        assert!((dis.p_success() - 0.25).abs() < 1e-9);
+        let elven = test.with_advantage(Advantage::ElvenAccuracy);
+        assert!((elven.p_success() - 0.875).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::action::{Condition, SaveKind};
+use crate::armor::Armor;
+use crate::basetraits::*;
+#[cfg(feature = "std")]
+use crate::combat::{AreaEffectDensity, RechargeModel};
+use crate::damage::{DamageKind, SystemShock};
+use crate::space::Area;
+use crate::spells::CasterKind;
+
+use core::fmt;
+
+/// The rulebooks cited throughout this crate.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Book {
+    /// Player's Handbook
+    PHB,
+    /// Dungeon Master's Guide
+    DMG,
+    /// Monster Manual
+    MM,
+}
+
+/// A page reference into a 5e rulebook.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub struct Citation {
+    pub book: Book,
+    pub page: usize,
+}
+
+impl Citation {
+    pub const fn phb(page: usize) -> Citation {
+        Citation { book: Book::PHB, page }
+    }
+
+    pub const fn dmg(page: usize) -> Citation {
+        Citation { book: Book::DMG, page }
+    }
+
+    pub const fn mm(page: usize) -> Citation {
+        Citation { book: Book::MM, page }
+    }
+}
+
+impl fmt::Display for Citation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "5e {:?}, p. {}", self.book, self.page)
+    }
+}
+
+/// Trait for types whose meaning is defined by a specific rule.
+pub trait Cited {
+    const CITATION: Citation;
+}
+
+/// Citations for the rules behind computed values, keyed by the path of the type or function
+/// implementing them. Types implementing `Cited` are listed by their `Cited::CITATION`.
+pub const REGISTRY: &[(&str, Citation)] = &[
+    ("Abilities", Abilities::CITATION),
+    ("AMods", AMods::CITATION),
+    ("AScores", AScores::CITATION),
+    ("Skill", Skill::CITATION),
+    ("Creature::passive_score", Citation::phb(175)),
+    ("Size", Size::CITATION),
+    ("Size::hit_die", Citation::dmg(276)),
+    ("Size::space_ft", Citation::phb(191)),
+    ("Size::carrying_multiplier", Citation::phb(176)),
+    ("Speeds", Speeds::CITATION),
+    ("Alignment", Alignment::CITATION),
+    ("CreatureType", CreatureType::CITATION),
+    ("CR", CR::CITATION),
+    ("ProfBonus", ProfBonus::CITATION),
+    ("HP", HP::CITATION),
+    ("XP", XP::CITATION),
+    ("XP::from(CR)", Citation::dmg(275)),
+    ("AC", AC::CITATION),
+    ("AC::from(CR)", Citation::dmg(274)),
+    ("ProfBonus::from(CR)", Citation::dmg(274)),
+    ("CR::from(HP)", Citation::dmg(274)),
+    ("CR::for_expected_damage", Citation::dmg(274)),
+    ("CR::to_hit_bonus", Citation::dmg(274)),
+    ("CR::save_dc", Citation::dmg(274)),
+    ("DamageKind", DamageKind::CITATION),
+    ("Armor", Armor::CITATION),
+    ("BaseCreature::damage_factor", Citation::phb(197)),
+    ("BaseCreature::defensive_cr", Citation::dmg(274)),
+    ("BaseCreature::offensive_cr", Citation::dmg(274)),
+    ("BaseCreature::compute_cr", Citation::dmg(274)),
+    ("Area", Area::CITATION),
+    ("AttackKind::modifier", Citation::phb(195)),
+    ("Attack::modifier", Citation::phb(194)),
+    ("SaveKind", SaveKind::CITATION),
+    ("Stacking::SameSourceDoesNotStack", Citation::phb(205)),
+    ("Stacking::BestInGroup", Citation::phb(196)),
+    #[cfg(feature = "std")]
+    ("AreaEffectDensity", AreaEffectDensity::CITATION),
+    #[cfg(feature = "std")]
+    ("RechargeModel", RechargeModel::CITATION),
+    ("CombatSettings::rounds", Citation::dmg(278)),
+    ("CasterKind", CasterKind::CITATION),
+    ("SystemShock", SystemShock::CITATION),
+    ("Condition", Condition::CITATION),
+    ("spells::SLOTS", Citation::phb(165)),
+    ("spells::pact_slots", Citation::phb(107)),
+];
+
+/// Look up the citation for a type or function by its path (see `REGISTRY`).
+pub fn citation_for(key: &str) -> Option<Citation> {
+    REGISTRY.iter().find(|(k, _)| *k == key).map(|(_, c)| *c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registered<T: Cited>(key: &str) {
+        assert_eq!(citation_for(key), Some(T::CITATION), "{}", key);
+    }
+
+    #[test]
+    fn cited_types() {
+        registered::<Abilities>("Abilities");
+        registered::<AMods>("AMods");
+        registered::<AScores>("AScores");
+        registered::<Skill>("Skill");
+        registered::<Size>("Size");
+        registered::<Speeds>("Speeds");
+        registered::<Alignment>("Alignment");
+        registered::<CreatureType>("CreatureType");
+        registered::<CR>("CR");
+        registered::<ProfBonus>("ProfBonus");
+        registered::<HP>("HP");
+        registered::<XP>("XP");
+        registered::<AC>("AC");
+        registered::<DamageKind>("DamageKind");
+        registered::<Armor>("Armor");
+        registered::<Area>("Area");
+        registered::<SaveKind>("SaveKind");
+        registered::<Condition>("Condition");
+        registered::<CasterKind>("CasterKind");
+        registered::<SystemShock>("SystemShock");
+        #[cfg(feature = "std")]
+        registered::<AreaEffectDensity>("AreaEffectDensity");
+        #[cfg(feature = "std")]
+        registered::<RechargeModel>("RechargeModel");
+        assert!(REGISTRY.iter().enumerate().all(|(idx, (k, _))| {
+            REGISTRY[..idx].iter().all(|(prev, _)| prev != k)
+        }));
+    }
+}
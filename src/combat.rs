@@ -1,11 +1,17 @@
 use crate::types::*;
 use crate::creature::*;
 use crate::action::*;
-use crate::dice::*;
 use crate::damage::*;
 use crate::basetraits::*;
+use crate::dice::{DiceExpr, Distribution, Value};
+use crate::check::*;
+use crate::explain::Derivation;
+use crate::citation::*;
 use crate::util;
 
+use rand::Rng;
+use std::sync::Arc;
+
 /// Expresses how many targets will be affected by an action that targets an area (`Target::Area`).
 /// Exactly indicates that only the exact number will be affected; Density indicates how many
 /// targets exist per area unit (usually feet). Density is usually fairly low: 0.04 would be one
@@ -17,6 +23,10 @@ pub enum AreaEffectDensity {
     Density(f64),
 }
 
+impl Cited for AreaEffectDensity {
+    const CITATION: Citation = Citation::dmg(278);
+}
+
 impl Default for AreaEffectDensity {
     fn default() -> AreaEffectDensity {
         AreaEffectDensity::Exactly(2)
@@ -28,16 +38,15 @@ impl Default for AreaEffectDensity {
 /// attack over three rounds (5e DMG, p. 278); the odds of getting a recharge after two turns is,
 /// in fact, 5 to 4 (greater than one half). To sate the book's calculations, the default is Never,
 /// but AfterPassProbability(0.5) is probably reasonable under less artificial circumstances.
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,Default)]
 pub enum RechargeModel {
+    #[default]
     Never,
     AfterPassProbability(f64),
 }
 
-impl Default for RechargeModel {
-    fn default() -> RechargeModel {
-        RechargeModel::Never
-    }
+impl Cited for RechargeModel {
+    const CITATION: Citation = Citation::dmg(278);
 }
 
 /// Contains some common settings used for combat calculations
@@ -47,6 +56,16 @@ pub struct CombatSettings {
     pub recharge_model: RechargeModel,
     /// Number of rounds for CR damage calculation; default is 3 (5e DMG, p. 278)
     pub rounds: usize,
+    /// Whether expected damage from attack rolls includes the chance of a critical hit (5e PHB,
+    /// p. 196). Off by default, as the DMG's damage figures don't include it.
+    pub crits: bool,
+    /// Rounds a melee attacker spends closing with the defenders (moving, or working around
+    /// threatened areas) before it can make melee attacks; fractions mean it gets part of a round.
+    /// The default is 0, as the DMG assumes everyone is engaged from the first round.
+    pub engagement_delay: f64,
+    /// Whether damage applied with `CombatPair::apply_damage` follows the optional massive
+    /// damage rule (5e DMG, p. 273). Off by default.
+    pub massive_damage: bool,
 }
 
 impl Default for CombatSettings {
@@ -55,7 +74,186 @@ impl Default for CombatSettings {
             effect_density: Default::default(),
             recharge_model: Default::default(),
             rounds: 3,
+            crits: false,
+            engagement_delay: 0.0,
+            massive_damage: false,
+        }
+    }
+}
+
+impl CombatSettings {
+    /// How many of the `rounds` (at least one) an action with the given uses can be taken in,
+    /// at most once a round. Uses per day or per rest, and charges (at one charge a use), are
+    /// all assumed to be spent in this encounter. A recharging action is taken once, and again
+    /// each time `recharge_model` deems it recharged.
+    pub fn expected_uses(&self, uses: &Uses) -> f64 {
+        let rounds = self.rounds.max(1);
+        let limit = |n: usize| n.min(rounds) as f64;
+        match uses {
+            Uses::Indefinite => rounds as f64,
+            Uses::PerDay(n) | Uses::PerShortRest(n) | Uses::PerLongRest(n) => limit(*n),
+            Uses::Charges { max, .. } => limit(*max),
+            Uses::ShortOrLongRest => 1.0,
+            Uses::Recharge(v, d) => match self.recharge_model {
+                RechargeModel::Never => 1.0,
+                RechargeModel::AfterPassProbability(p) => {
+                    // The recharge rolls it takes for the chance of having recharged to reach p.
+                    let p_miss = (v - 1) as f64 / d.sides() as f64;
+                    let (mut rolls, mut missed) = (1, p_miss);
+                    while 1.0 - missed < p && rolls < rounds {
+                        rolls += 1;
+                        missed *= p_miss;
+                    }
+                    (1 + (rounds - 1) / rolls) as f64
+                },
+            },
+        }
+    }
+}
+
+/// Expected damage of the attack by the pair's attacker against its defenders; see
+/// `CombatPair::expected_damage`.
+impl<'a, 'd, 's> ExpectedValueIn<CombatPair<'a, 'd, 's>> for Attack {
+    fn expected_in(&self, pair: &CombatPair<'a, 'd, 's>) -> f64 {
+        pair.expected_damage(self)
+    }
+}
+
+impl Attack {
+    /// Expected damage of this attack, made by `attacker`, against `Creature::reference` at the
+    /// attacker's CR as a baseline (see `CombatPair::with_baseline`).
+    pub fn expected_damage_vs_default(&self, attacker: &Creature, settings: &CombatSettings) -> f64 {
+        let defender = Creature::reference(attacker.cr());
+        self.expected_in(&CombatPair::new(attacker, &defender, settings).with_baseline())
+    }
+}
+
+/// Expected damage of all of the attacks (of a Multiattack, for instance).
+impl<'a, 'd, 's> ExpectedValueIn<CombatPair<'a, 'd, 's>> for ActionKind {
+    fn expected_in(&self, pair: &CombatPair<'a, 'd, 's>) -> f64 {
+        pair.expected_action_damage(self)
+    }
+}
+
+impl<'a, 'd, 's> ExpectedValueIn<CombatPair<'a, 'd, 's>> for Action {
+    fn expected_in(&self, pair: &CombatPair<'a, 'd, 's>) -> f64 {
+        self.kind.expected_in(pair)
+    }
+}
+
+/// How to order creatures with the same initiative total. The PHB leaves this to the DM (and
+/// the players, among themselves; 5e PHB, p. 189); the DMG suggests the higher Dex score.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub enum InitiativeTieBreak {
+    /// Higher Dex score first, then in the order given.
+    #[default]
+    Dexterity,
+    /// Tied creatures roll again (repeatedly, if need be).
+    Reroll,
+    /// In the order given, as the DM decides.
+    Given,
+}
+
+/// Roll initiative for the creatures, returning each one's index and total, in turn order.
+pub fn roll_initiative<R: Rng>(
+    creatures: &[&Creature], tie: InitiativeTieBreak, rng: &mut R,
+) -> Vec<(usize, Value)> {
+    let mut rolls: Vec<_> = creatures.iter().enumerate().map(|(idx, c)| {
+        let total = c.d20().roll_value(rng) + c.initiative_modifier();
+        let key = match tie {
+            InitiativeTieBreak::Dexterity => c.ascores().0.dex,
+            InitiativeTieBreak::Reroll | InitiativeTieBreak::Given => 0,
+        };
+        (idx, total, key)
+    }).collect();
+    rolls.sort_by(|(ia, ta, ka), (ib, tb, kb)| tb.cmp(ta).then(kb.cmp(ka)).then(ia.cmp(ib)));
+    let mut order: Vec<_> = rolls.into_iter().map(|(idx, total, _)| (idx, total)).collect();
+    if tie == InitiativeTieBreak::Reroll {
+        let mut start = 0;
+        while start < order.len() {
+            let end = start + order[start..].iter().take_while(|(_, t)| *t == order[start].1).count();
+            reroll_ties(creatures, &mut order[start..end], rng);
+            start = end;
+        }
+    }
+    order
+}
+
+/// Reorder creatures with the same initiative total by rolling initiative again among them, and
+/// again among any which tie on that, until none are tied. Their totals are left as they were.
+fn reroll_ties<R: Rng>(creatures: &[&Creature], tied: &mut [(usize, Value)], rng: &mut R) {
+    if tied.len() < 2 {
+        return;
+    }
+    let mut rerolls: Vec<_> = tied.iter().map(|&(idx, total)| {
+        let c = creatures[idx];
+        (idx, total, c.d20().roll_value(rng) + c.initiative_modifier())
+    }).collect();
+    rerolls.sort_by(|(_, _, ra), (_, _, rb)| rb.cmp(ra));
+    for (slot, (idx, total, _)) in tied.iter_mut().zip(&rerolls) {
+        *slot = (*idx, *total);
+    }
+    let mut start = 0;
+    while start < rerolls.len() {
+        let end = start + rerolls[start..].iter().take_while(|(_, _, r)| *r == rerolls[start].2).count();
+        reroll_ties(creatures, &mut tied[start..end], rng);
+        start = end;
+    }
+}
+
+/// One side of a `Comparison`: a creature's own statistics, and how it fares against the other.
+#[derive(Debug,Clone)]
+pub struct Profile {
+    pub cr: CR,
+    pub hit_points: HP,
+    pub armor_class: AC,
+    /// The fastest of its movement speeds.
+    pub top_speed: usize,
+    pub controller: bool,
+    /// Expected damage per round against the other creature.
+    pub round_damage: f64,
+    /// The best chance to hit the other creature with any of its attack rolls.
+    pub hit_chance: f64,
+    /// Rounds of `round_damage` to exhaust the other creature's expected hit points; `None` if it
+    /// deals no damage.
+    pub rounds_to_kill: Option<f64>,
+}
+
+/// A side-by-side comparison of two creatures; see `compare`.
+#[derive(Debug,Clone)]
+pub struct Comparison {
+    pub first: Profile,
+    pub second: Profile,
+}
+
+/// Compare two creatures' offense, defense, and mobility, each measured against the other.
+pub fn compare(first: &Creature, second: &Creature, settings: &CombatSettings) -> Comparison {
+    let profile = |me: &Creature, other: &Creature| {
+        let pair = CombatPair::new(me, other, settings);
+        let round_damage = pair.expected_round_damage();
+        let speeds = me.speeds();
+        Profile {
+            cr: me.cr(),
+            hit_points: me.expected_hit_points(),
+            armor_class: me.armor_class(),
+            top_speed: [speeds.walk, speeds.fly, speeds.swim, speeds.climb, speeds.burrow]
+                .iter().cloned().max().unwrap_or(0),
+            controller: me.roles().contains(&Role::Controller),
+            round_damage,
+            hit_chance: me.actions().iter().flat_map(|act| act.kind.attacks())
+                .filter(|atk| atk.save.is_none())
+                .map(|atk| pair.hit_chance(atk))
+                .fold(0.0, f64::max),
+            rounds_to_kill: if round_damage > 0.0 {
+                Some(other.expected_hit_points().0 as f64 / round_damage)
+            } else {
+                None
+            },
         }
+    };
+    Comparison {
+        first: profile(first, second),
+        second: profile(second, first),
     }
 }
 
@@ -66,51 +264,410 @@ pub struct CombatPair<'a, 'd ,'s> {
     attacker: &'a Creature,
     defenders: &'d Creature,
     settings: &'s CombatSettings,
+    /// Whether action-denial effects count as damage; off when valuing what they deny, so that
+    /// two controllers don't value each other indefinitely.
+    value_control: bool,
+    /// The attacker's advantage on attack rolls.
+    advantage: Advantage,
+    /// Whether the defenders stand in for the DMG's damage baseline; see `with_baseline`.
+    baseline: bool,
 }
 
 impl<'a, 'd, 's> CombatPair<'a, 'd, 's> {
+    pub fn new(attacker: &'a Creature, defenders: &'d Creature, settings: &'s CombatSettings) -> CombatPair<'a, 'd, 's> {
+        CombatPair { attacker, defenders, settings, value_control: true, advantage: Advantage::None, baseline: false }
+    }
+
+    /// The pair with the attacker making attack rolls with advantage or disadvantage.
+    pub fn with_advantage(mut self, advantage: Advantage) -> CombatPair<'a, 'd, 's> {
+        self.advantage = advantage;
+        self
+    }
+
+    /// The pair with the defenders measured as the DMG measures damage for CR (5e DMG, p. 278):
+    /// they fail every save, and each round of actions they're denied is worth the damage per
+    /// round expected at their CR, rather than what their own actions (if any) would deal.
+    pub fn with_baseline(mut self) -> CombatPair<'a, 'd, 's> {
+        self.baseline = true;
+        self
+    }
+
+    /// The die the attacker rolls for attack rolls.
+    pub fn attack_die(&self) -> DiceExpr {
+        self.advantage.apply(self.attacker.d20())
+    }
+
+    /// The pair with attacker and defender swapped.
+    fn reversed(&self) -> CombatPair<'d, 'a, 's> {
+        CombatPair {
+            attacker: self.defenders,
+            defenders: self.attacker,
+            settings: self.settings,
+            value_control: false,
+            advantage: Advantage::None,
+            baseline: false,
+        }
+    }
+
     pub fn expected_targets(&self, atk: &Attack) -> usize {
         match &atk.target {
             Target::Exactly(n) => *n,
-            Target::Area(a) => match self.settings.effect_density {
-                AreaEffectDensity::Exactly(n) => n,
-                AreaEffectDensity::Density(f) => (f * a.floor_area()) as usize,
+            Target::Area(a) => {
+                let n = match self.settings.effect_density {
+                    AreaEffectDensity::Exactly(n) => n,
+                    AreaEffectDensity::Density(f) => (f * a.floor_area()) as usize,
+                };
+                #[cfg(feature = "tracing")]
+                tracing::trace!(area = ?a, targets = n, "area targets");
+                n
+            },
+        }
+    }
+
+    /// Expected damage of a roll of the given kind after the defender's defenses.
+    fn expected_roll_damage(&self, ex: &DiceExpr, bonus: isize, k: DamageKind) -> f64 {
+        ex.distribution().0.iter().map(|(v, p)| {
+            p * (mitigate(self.defenders, Damage(util::clamp_isize(v + bonus), k)) as f64)
+        }).sum()
+    }
+
+    /// The kind of damage to choose for the first damage roll, if the attack offers a choice:
+    /// whichever does the most expected damage against the defender's defenses.
+    pub fn best_damage_kind(&self, atk: &Attack) -> Option<DamageKind> {
+        let DamageRoll(ex, _) = atk.dmg_rolls.first()?;
+        atk.dmg_kind_choices.iter().cloned().map(|k| {
+            (k, self.expected_roll_damage(ex, atk.dmg_bonus, k))
+        }).fold(None, |best: Option<(DamageKind, f64)>, (k, e)| match best {
+            Some((_, be)) if be >= e => best,
+            _ => Some((k, e)),
+        }).map(|(k, _)| k)
+    }
+
+    /// The attack roll of an attack against the defender's AC.
+    pub fn attack_roll(&self, atk: &Attack) -> D20Test {
+        self.attack_roll_with(atk, self.advantage)
+    }
+
+    /// As `attack_roll`, with the given advantage in place of the pair's.
+    fn attack_roll_with(&self, atk: &Attack, advantage: Advantage) -> D20Test {
+        D20Test::new(
+            D20TestKind::AttackRoll, advantage.apply(self.attacker.d20()),
+            self.attack_modifier(atk), self.defenders.armor_class().0 as isize,
+        )
+    }
+
+    /// The weights of an ordinary hit and of a critical hit in the expected damage of an attack,
+    /// made with the given advantage: their chances relative to the chance to hit without
+    /// advantage. The DMG's damage figures presume an ordinary hit, so these sum to 1 without
+    /// advantage or disadvantage, and are 1 and 0 for an attack with a save. Advantage thus
+    /// counts once, through the chances of both.
+    fn hit_weights(&self, atk: &Attack, advantage: Advantage) -> (f64, f64) {
+        if atk.save.is_some() {
+            return (1.0, 0.0);
+        }
+        let test = self.attack_roll_with(atk, advantage);
+        let p_crit = if self.settings.crits { test.p_crit() } else { 0.0 };
+        let p_plain = self.attack_roll_with(atk, Advantage::None).p_success();
+        ((test.p_success() - p_crit) / p_plain, p_crit / p_plain)
+    }
+
+    /// The chance that an attack hits the defender, counting natural 1s and 20s.
+    pub fn hit_chance(&self, atk: &Attack) -> f64 {
+        self.attack_roll(atk).p_success()
+    }
+
+    /// The distribution of the number of the action's attacks which hit the defender.
+    pub fn action_hits(&self, act: &Action) -> Distribution {
+        let ps: Vec<f64> = act.kind.attacks().map(|atk| self.hit_chance(atk)).collect();
+        success_count(&ps)
+    }
+
+    /// Expected damage of an attack, accounting for the chance to hit the defender with the given
+    /// advantage in place of the pair's (rather than assuming a hit, as the DMG's figures do).
+    pub fn expected_hit_damage(&self, atk: &Attack, advantage: Advantage) -> f64 {
+        let p_plain = self.attack_roll_with(atk, Advantage::None).p_success();
+        p_plain * self.single_damage_rolls_with(atk, advantage).iter().map(|(_, e)| e).sum::<f64>()
+    }
+
+    /// The chance of shoving the defender prone: the attacker's Athletics check must beat the
+    /// defender's better of Athletics and Acrobatics, and the defender may be at most one size
+    /// larger (5e PHB, p. 195).
+    pub fn shove_chance(&self) -> f64 {
+        if self.defenders.size() as usize > self.attacker.size() as usize + 1 {
+            return 0.0;
+        }
+        let defense = self.defenders.skill_modifier(Skill::Athletics)
+            .max(self.defenders.skill_modifier(Skill::Acrobatics));
+        p_exceeds(
+            &self.attacker.d20(), self.attacker.skill_modifier(Skill::Athletics),
+            &self.defenders.d20(), defense,
+        )
+    }
+
+    /// Expected damage of an action's attacks, accounting for the chance to hit (see
+    /// `expected_hit_damage`).
+    pub fn expected_action_hit_damage(&self, act: &Action) -> f64 {
+        act.kind.attacks().map(|atk| self.expected_hit_damage(atk, self.advantage)).sum()
+    }
+
+    /// As `expected_action_hit_damage`, but spending the first attack on shoving the defender
+    /// prone, so that the remaining melee attacks gain advantage if it succeeds (5e PHB,
+    /// p. 292), combined with the pair's own (see `Advantage::combine`). Comparing the two
+    /// answers whether shoving is worth an attack. Ranged attacks are left as they are, though
+    /// they would have disadvantage against a prone target.
+    pub fn expected_shove_then_attack_damage(&self, act: &Action) -> f64 {
+        let p_shove = self.shove_chance();
+        act.kind.attacks().skip(1).map(|atk| {
+            let normal = self.expected_hit_damage(atk, self.advantage);
+            match atk.kind {
+                AttackKind::Melee => {
+                    let prone = self.expected_hit_damage(atk, self.advantage.combine(Advantage::Advantage));
+                    p_shove * prone + (1.0 - p_shove) * normal
+                },
+                _ => normal,
             }
+        }).sum()
+    }
+
+    /// The chance that the defender is surprised by the attacker: the attacker's Stealth check
+    /// must exceed the defender's passive Perception, or the defender notices it (5e PHB,
+    /// p. 189).
+    pub fn surprise_chance(&self) -> f64 {
+        let passive = self.defenders.passive_score(Skill::Perception, Advantage::None);
+        D20Test::new(
+            D20TestKind::AbilityCheck, self.attacker.d20(),
+            self.attacker.skill_modifier(Skill::Stealth), passive + 1,
+        ).p_success()
+    }
+
+    /// The chance that an attack is a critical hit, if `CombatSettings::crits` is on and the
+    /// attack uses an attack roll (rather than a save).
+    pub fn crit_chance(&self, atk: &Attack) -> f64 {
+        if self.settings.crits && atk.save.is_none() {
+            self.attack_roll(atk).p_crit()
+        } else {
+            0.0
         }
     }
 
-    pub fn expected_single_damage_rolls(&self, atk: &Attack) -> Vec<Damage> {
-        atk.dmg_rolls.iter().enumerate().map(|(idx, DamageRoll(ex, k))| {
-            Damage(
-                util::clamp_isize(0.0f64.max(ex.expected() * self.defenders.damage_factor(*k)) as isize
-                                  + if idx == 0 { atk.dmg_bonus } else { 0 }
-                ),
-                *k
-            )
+    /// Expected damage of each kind after the defender's defenses, in order of first
+    /// appearance. As with `DamageInstance::mitigate`, the rolls of each kind are totalled
+    /// before defenses apply, so 1d6 fire + 1d4 fire against resistance halves the sum once.
+    /// The attack's damage bonus is added to the first roll, whose kind is chosen per
+    /// `best_damage_kind`. Critical hits and the attacker's advantage are weighted in per
+    /// `hit_weights`.
+    pub fn expected_single_damage_rolls(&self, atk: &Attack) -> Vec<(DamageKind, f64)> {
+        self.single_damage_rolls_with(atk, self.advantage)
+    }
+
+    /// As `expected_single_damage_rolls`, with the given advantage in place of the pair's.
+    fn single_damage_rolls_with(&self, atk: &Attack, advantage: Advantage) -> Vec<(DamageKind, f64)> {
+        let chosen = self.best_damage_kind(atk);
+        let (w_hit, w_crit) = self.hit_weights(atk, advantage);
+        let normal = Self::totals_by_kind(&atk.dmg_rolls, atk.dmg_bonus, chosen);
+        let crits = Self::totals_by_kind(&atk.crit_damage_rolls(), atk.dmg_bonus, chosen);
+        normal.iter().zip(crits.iter()).map(|((k, ex), (_, crit))| {
+            let mut expected = w_hit * self.expected_roll_damage(ex, 0, *k);
+            if w_crit > 0.0 {
+                expected += w_crit * self.expected_roll_damage(crit, 0, *k);
+            }
+            (*k, expected)
         }).collect()
     }
 
-    pub fn expected_single_damage_sum(&self, atk: &Attack) -> usize {
-        self.expected_single_damage_rolls(atk).iter().map(|Damage(u, _)| u).sum()
+    /// The total of each kind of damage among `rolls` as one expression, in order of first
+    /// appearance, clamping each roll at 0 as `Attack::roll_damage` does.
+    fn totals_by_kind(rolls: &[DamageRoll], bonus: isize, chosen: Option<DamageKind>) -> Vec<(DamageKind, DiceExpr)> {
+        let mut totals: Vec<(DamageKind, DiceExpr)> = Vec::new();
+        for (idx, DamageRoll(ex, k)) in rolls.iter().enumerate() {
+            let (bonus, k) = if idx == 0 { (bonus, chosen.unwrap_or(*k)) } else { (0, *k) };
+            let term = DiceExpr::Minimum(0, Arc::new(DiceExpr::Plus(
+                Arc::new(ex.clone()), Arc::new(DiceExpr::Const(bonus)),
+            )));
+            match totals.iter_mut().find(|(tk, _)| *tk == k) {
+                Some((_, total)) => *total = DiceExpr::Plus(Arc::new(total.clone()), Arc::new(term)),
+                None => totals.push((k, term)),
+            }
+        }
+        totals
+    }
+
+    pub fn expected_single_damage_sum(&self, atk: &Attack) -> f64 {
+        self.expected_single_damage_rolls(atk).iter().map(|(_, e)| e).sum()
+    }
+
+    pub fn expected_single_damage(&self, atk: &Attack) -> f64 {
+        self.single_damage(self.expected_single_damage_sum(atk), atk).max(0.0)
+    }
+
+    /// The save DC of an attack's saving throw, the defender's modifier to it, and the chance
+    /// that the defender passes (none against a baseline; see `with_baseline`).
+    fn save_odds(&self, sk: &SaveKind, sdc: &SavingDC) -> (usize, isize, f64) {
+        let dc = sdc.def_class(&self.attacker.mods(), self.attacker.prof_bonus());
+        let sm = match sk {
+            SaveKind::Ability(ab) => self.defenders.save_modifier(*ab),
+            SaveKind::Death => sk.modifier(&self.defenders.mods()),
+        };
+        let p_pass = if self.baseline {
+            0.0
+        } else {
+            D20Test::new(D20TestKind::SavingThrow, self.defenders.d20(), sm, dc as isize).p_success()
+        };
+        (dc, sm, p_pass)
+    }
+
+    /// The damage value of the actions denied by a failed save.
+    fn denied_value(&self, cv: &ControlValue) -> f64 {
+        match cv {
+            _ if !self.value_control => 0.0,
+            ControlValue::Damage(d) => *d as f64,
+            ControlValue::DeniedRounds(r) if self.baseline => r * self.defenders.cr().expected_damage() as f64,
+            ControlValue::DeniedRounds(r) => r * self.reversed().expected_round_damage(),
+        }
     }
 
-    pub fn expected_single_damage(&self, atk: &Attack) -> usize {
-        let mut dmg = self.expected_single_damage_sum(atk) as isize + atk.dmg_bonus;
+    /// Expected damage to one target, given the expected damage of its rolls, accounting for the
+    /// attack's save.
+    fn single_damage(&self, rolled: f64, atk: &Attack) -> f64 {
+        let mut dmg = rolled;
         if let Some(Save(sk, sdc, sef)) = &atk.save {
-            let dc = sdc.def_class(&self.attacker.mods(), self.attacker.prof_bonus());
-            let sm = sk.modifier(&self.defenders.mods());
+            let (_dc, _sm, p_pass) = self.save_odds(sk, sdc);
             match sef {
                 SaveEffect::ReducesDamage(amt) => {
-                    let p_pass = DiceExpr::Die(Die(20)).prob_pass((dc as isize) - sm);
-                    dmg = (p_pass * ((dmg as f64) * amt) + (1.0 - p_pass) * (dmg as f64)) as isize;
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!(dc = _dc, save_modifier = _sm, p_pass, "save reduces damage");
+                    dmg = p_pass * dmg * amt + (1.0 - p_pass) * dmg;
+                },
+                SaveEffect::DeniesActions(cv) => {
+                    let denied = self.denied_value(cv);
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!(dc = _dc, save_modifier = _sm, p_pass, denied, "save denies actions");
+                    dmg += (1.0 - p_pass) * denied;
                 },
             };
         }
-        util::clamp_isize(dmg)
+        #[cfg(feature = "tracing")]
+        tracing::debug!(damage = dmg, "expected single-target damage");
+        dmg
+    }
+
+    /// As `expected_single_damage`, with its derivation.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn explain_expected_single_damage(&self, atk: &Attack) -> Derivation {
+        let rolls = self.expected_single_damage_rolls(atk);
+        let rolled = rolls.iter().map(|(_, e)| e).sum();
+        let sum = rolls.iter().fold(
+            Derivation::new("expected damage rolls", rolled),
+            |d, (k, e)| d.with(
+                Derivation::new(format!("{:?} damage after defenses", k), *e)
+                    .cite(Citation::phb(197))
+            ),
+        );
+        let mut children = vec![sum];
+        if atk.save.is_none() && (self.advantage != Advantage::None || self.settings.crits) {
+            let (w_hit, w_crit) = self.hit_weights(atk, self.advantage);
+            children.push(Derivation::new("weight of an ordinary hit", w_hit).cite(Citation::phb(173)));
+            children.push(Derivation::new("weight of a critical hit", w_crit).cite(Citation::phb(196)));
+        }
+        if let Some(Save(sk, sdc, sef)) = &atk.save {
+            let (dc, sm, p_pass) = self.save_odds(sk, sdc);
+            children.push(
+                Derivation::new("probability of passing the save", p_pass)
+                    .cite(SaveKind::CITATION)
+                    .with(Derivation::new("save DC", dc as f64))
+                    .with(Derivation::new("save modifier", sm as f64))
+            );
+            children.push(match sef {
+                SaveEffect::ReducesDamage(amt) => Derivation::new("fraction of damage on a pass", *amt),
+                SaveEffect::DeniesActions(cv) => {
+                    Derivation::new("damage value of denied actions", self.denied_value(cv))
+                },
+            });
+        }
+        let dmg = self.single_damage(rolled, atk).max(0.0);
+        let mut d = Derivation::new("expected damage to one target", dmg);
+        d.children = children;
+        d
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn expected_damage(&self, atk: &Attack) -> f64 {
+        self.expected_single_damage(atk) * self.expected_targets(atk) as f64
+    }
+
+    /// As `expected_damage`, with its derivation.
+    pub fn explain_expected_damage(&self, atk: &Attack) -> Derivation {
+        let targets = Derivation::new("expected targets", self.expected_targets(atk) as f64);
+        let targets = match atk.target {
+            Target::Area(_) => targets.cite(AreaEffectDensity::CITATION),
+            _ => targets,
+        };
+        Derivation::new("expected damage", self.expected_damage(atk))
+            .with(self.explain_expected_single_damage(atk))
+            .with(targets)
+    }
+
+    /// Expected damage of one use of an action (all of the attacks of a Multiattack).
+    pub fn expected_action_damage(&self, kind: &ActionKind) -> f64 {
+        kind.attacks().map(|atk| self.expected_damage(atk)).sum()
+    }
+
+    /// Expected damage per round over `CombatSettings::rounds`, taking the attacker's most
+    /// damaging action in as many rounds as its uses allow (see `CombatSettings::expected_uses`),
+    /// the next most damaging in as many of the rest, and so on.
+    pub fn expected_round_damage(&self) -> f64 {
+        self.planned_round_damage(|_| true)
+    }
+
+    /// As `expected_round_damage`, with only the actions which make no melee attacks, for
+    /// rounds before the attacker is engaged.
+    pub fn expected_unengaged_round_damage(&self) -> f64 {
+        self.planned_round_damage(|act| !act.kind.attacks().any(|atk| {
+            matches!(atk.kind, AttackKind::Melee)
+        }))
+    }
+
+    fn planned_round_damage<F: Fn(&Action) -> bool>(&self, usable: F) -> f64 {
+        let mut options: Vec<(f64, f64)> = self.attacker.actions().iter().filter(|act| usable(act))
+            .map(|act| (self.expected_action_damage(&act.kind), self.settings.expected_uses(&act.uses)))
+            .collect();
+        options.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        let rounds = self.settings.rounds.max(1) as f64;
+        let (mut left, mut total) = (rounds, 0.0);
+        for (dmg, uses) in options {
+            let taken = uses.min(left);
+            total += taken * dmg;
+            left -= taken;
+        }
+        total / rounds
     }
 
-    pub fn expected_damage(&self, atk: &Attack) -> usize {
-        self.expected_single_damage(atk) * self.expected_targets(atk)
+    /// Expected damage over `CombatSettings::rounds`, where the attacker can only use actions
+    /// without melee attacks until `CombatSettings::engagement_delay` has passed.
+    pub fn expected_total_damage(&self) -> f64 {
+        let (engaged, unengaged) = (self.expected_round_damage(), self.expected_unengaged_round_damage());
+        (0..self.settings.rounds).map(|r| {
+            let frac = ((r + 1) as f64 - self.settings.engagement_delay).clamp(0.0, 1.0);
+            frac * engaged + (1.0 - frac) * unengaged
+        }).sum()
+    }
+
+    /// Apply an instance of damage to the defender's hit points (see
+    /// `HitPoints::apply_instance`), then the massive damage rule against its expected hit
+    /// points if `CombatSettings::massive_damage` is on.
+    pub fn apply_damage<R: Rng>(
+        &self, hp: &mut HitPoints, inst: &DamageInstance, rng: &mut R,
+    ) -> (DamageTaken, Option<SystemShock>) {
+        let taken = hp.apply_instance(self.defenders, inst);
+        let shock = if self.settings.massive_damage {
+            hp.apply_massive_damage(
+                &taken, self.defenders.expected_hit_points(), &self.defenders.d20(),
+                self.defenders.save_modifier(Ability::Con), rng,
+            )
+        } else {
+            None
+        };
+        (taken, shock)
     }
 
     pub fn attack_modifier(&self, atk: &Attack) -> isize {
@@ -119,7 +676,182 @@ impl<'a, 'd, 's> CombatPair<'a, 'd, 's> {
 
     pub fn expected_hit_ac(&self, atk: &Attack) -> AC {
         AC(util::clamp_isize(
-            (DiceExpr::Die(Die(20)).expected() + self.attack_modifier(atk) as f64) as isize
+            (self.attack_die().expected() + self.attack_modifier(atk) as f64) as isize
         ))
     }
+
+    /// As `expected_hit_ac`, with its derivation.
+    pub fn explain_expected_hit_ac(&self, atk: &Attack) -> Derivation {
+        let stack = atk.modifier_stack(&self.attacker.mods(), self.attacker.prof_bonus());
+        let modifier = stack.applied().into_iter().fold(
+            Derivation::new("attack modifier", stack.total() as f64).cite(Citation::phb(194)),
+            |d, (m, counts)| d.with(Derivation::new(
+                format!("{:?}{}", m.source, if counts { "" } else { " (does not stack)" }),
+                if counts { m.effective() as f64 } else { 0.0 },
+            )),
+        );
+        Derivation::new("expected AC hit", self.expected_hit_ac(atk).0 as f64)
+            .with(Derivation::new("expected d20 roll", self.attack_die().expected()))
+            .with(modifier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn damage_by_kind() {
+        let attacker = Creature::reference(CR::CR1);
+        let mut defender = BaseCreature::reference(CR::CR1);
+        defender.resistances.insert(DamageKind::Fire);
+        let defender = defender.with_cr(CR::CR1);
+        let settings = CombatSettings::default();
+        let pair = CombatPair::new(&attacker, &defender, &settings);
+        // Resistance halves the 6 fire once, not each 3 separately (rounding each down to 1).
+        let atk: Attack = "special; damage: 3 fire, 2 slashing, 3 fire".parse().unwrap();
+        assert_eq!(
+            pair.expected_single_damage_rolls(&atk),
+            vec![(DamageKind::Fire, 3.0), (DamageKind::Slashing, 2.0)],
+        );
+        let (inst, _) = atk.roll_damage(&mut StdRng::seed_from_u64(0));
+        assert_eq!(inst.mitigate(&defender), 5);
+    }
+
+    #[test]
+    fn fractional_damage() {
+        let attacker = Creature::reference(CR::CR1);
+        let defender = Creature::reference(CR::CR1);
+        let settings = CombatSettings::default();
+        let pair = CombatPair::new(&attacker, &defender, &settings);
+        // Each attack expects half a point of damage, which isn't lost to rounding before summing.
+        let atk: Arc<Attack> = Arc::new("special; damage: 1d2-1 piercing".parse().unwrap());
+        assert_eq!(pair.expected_damage(&atk), 0.5);
+        let kind = ActionKind::Multiattack(vec![atk.clone(), atk.clone(), atk]);
+        assert_eq!(pair.expected_action_damage(&kind), 1.5);
+        assert_eq!(kind.expected_in(&pair), 1.5);
+    }
+
+    #[test]
+    fn baseline() {
+        let attacker = Creature::reference(CR::CR1);
+        let defender = Creature::reference(CR::CR1);
+        let settings = CombatSettings::default();
+        let pair = CombatPair::new(&attacker, &defender, &settings);
+        let baseline = CombatPair::new(&attacker, &defender, &settings).with_baseline();
+        let half: Attack = "save dex DC 13 half; damage: 10 fire".parse().unwrap();
+        assert!(pair.expected_damage(&half) < 10.0);
+        assert_eq!(baseline.expected_damage(&half), 10.0);
+        let hold = Attack {
+            save: Some(Save(
+                SaveKind::Ability(Ability::Wis),
+                SavingDC::Exactly(13),
+                SaveEffect::DeniesActions(ControlValue::DeniedRounds(1.0)),
+            )),
+            ..Default::default()
+        };
+        assert_eq!(pair.expected_damage(&hold), 0.0);
+        assert_eq!(baseline.expected_damage(&hold), CR::CR1.expected_damage() as f64);
+    }
+
+    #[test]
+    fn advantage() {
+        let attacker = Creature::reference(CR::CR1);
+        let defender = Creature::reference(CR::CR1);
+        let settings = CombatSettings::default();
+        let atk: Attack = "melee; hit +3; damage: 20 slashing".parse().unwrap();
+        let plain = CombatPair::new(&attacker, &defender, &settings);
+        let adv = CombatPair::new(&attacker, &defender, &settings).with_advantage(Advantage::Advantage);
+        let dis = CombatPair::new(&attacker, &defender, &settings).with_advantage(Advantage::Disadvantage);
+        assert_eq!(plain.expected_damage(&atk), 20.0);
+        assert!(adv.expected_damage(&atk) > 20.0 && dis.expected_damage(&atk) < 20.0);
+        // The pair's own advantage isn't applied on top of the one asked for.
+        assert_eq!(
+            adv.expected_hit_damage(&atk, Advantage::None),
+            plain.expected_hit_damage(&atk, Advantage::None),
+        );
+        // With crits, advantage counts once, through the chances of a hit and of a crit.
+        let settings = CombatSettings { crits: true, ..Default::default() };
+        let plain = CombatPair::new(&attacker, &defender, &settings);
+        let adv = CombatPair::new(&attacker, &defender, &settings).with_advantage(Advantage::Advantage);
+        let atk: Attack = "melee; hit +3; damage: 4d6 slashing".parse().unwrap();
+        let (p_plain, test) = (plain.hit_chance(&atk), adv.attack_roll(&atk));
+        assert!((plain.expected_damage(&atk) - (14.0 * (p_plain - 0.05) + 28.0 * 0.05) / p_plain).abs() < 1e-9);
+        let expected = (14.0 * (test.p_success() - test.p_crit()) + 28.0 * test.p_crit()) / p_plain;
+        assert!((adv.expected_damage(&atk) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn limited_uses() {
+        let mut base = BaseCreature::reference(CR::CR1);
+        base.actions.push(Action::parse("Claw", "special; damage: 10 slashing").unwrap());
+        base.actions.push(Action::parse("Breath", "special; damage: 40 fire; recharge 5-6").unwrap());
+        let attacker = base.clone().with_cr(CR::CR1);
+        let defender = Creature::reference(CR::CR1);
+        let settings = CombatSettings::default();
+        let pair = CombatPair::new(&attacker, &defender, &settings);
+        // Breath in the first round and claws in the other two (5e DMG, p. 278).
+        assert_eq!(pair.expected_round_damage(), 20.0);
+        assert_eq!(pair.expected_total_damage(), 60.0);
+        // Recharge 5-6 has recharged with more than even odds after two rolls.
+        let recharging = CombatSettings { recharge_model: RechargeModel::AfterPassProbability(0.5), ..Default::default() };
+        assert_eq!(recharging.expected_uses(&Uses::Recharge(5, crate::dice::Die::d6())), 2.0);
+        assert_eq!(CombatPair::new(&attacker, &defender, &recharging).expected_round_damage(), 30.0);
+        assert_eq!(settings.expected_uses(&Uses::PerDay(5)), 3.0);
+        assert_eq!(settings.expected_uses(&Uses::ShortOrLongRest), 1.0);
+    }
+
+    #[test]
+    fn comparison() {
+        let a = Creature::reference(CR::CR1);
+        let settings = CombatSettings::default();
+        let cmp = compare(&a, &a, &settings);
+        assert_eq!((cmp.first.round_damage, cmp.first.rounds_to_kill), (0.0, None));
+    }
+
+    #[test]
+    fn massive_damage() {
+        let attacker = Creature::reference(CR::CR1);
+        let defender = Creature::reference(CR::CR1);
+        let max_hp = defender.expected_hit_points();
+        let blow = DamageInstance(vec![Damage(max_hp.0 / 2, DamageKind::Bludgeoning)]);
+        let mut rng = StdRng::seed_from_u64(0);
+        let settings = CombatSettings::default();
+        let mut hp = HitPoints { current: max_hp, temporary: HP(0) };
+        let (taken, shock) = CombatPair::new(&attacker, &defender, &settings).apply_damage(&mut hp, &blow, &mut rng);
+        assert!(taken.is_massive(max_hp) && shock.is_none());
+        let settings = CombatSettings { massive_damage: true, ..Default::default() };
+        let pair = CombatPair::new(&attacker, &defender, &settings);
+        // A +0 save against DC 15 fails 70% of the time, so some of these are all but certain to.
+        let shocks = (0..50).filter(|_| {
+            let mut hp = HitPoints { current: max_hp, temporary: HP(0) };
+            pair.apply_damage(&mut hp, &blow, &mut rng).1.is_some()
+        }).count();
+        assert!(shocks > 0);
+    }
+
+    #[test]
+    fn initiative_ties() {
+        let a = Creature::reference(CR::CR1);
+        let mut b = BaseCreature::reference(CR::CR1);
+        b.initiative_bonus = 100;
+        let b = b.with_cr(CR::CR1);
+        let creatures = [&a, &a, &b];
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut firsts = [0; 2];
+        for _ in 0..2000 {
+            let order = roll_initiative(&creatures, InitiativeTieBreak::Reroll, &mut rng);
+            assert_eq!(order[0].0, 2);
+            let mut seen: Vec<_> = order.iter().map(|(idx, _)| *idx).collect();
+            seen.sort_unstable();
+            assert_eq!(seen, vec![0, 1, 2]);
+            if order[1].1 == order[2].1 {
+                firsts[order[1].0] += 1;
+            }
+        }
+        // Tied creatures go in either order, rather than always as given.
+        assert!(firsts[0] > 0 && firsts[1] > 0);
+    }
 }
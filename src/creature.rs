@@ -1,27 +1,70 @@
 use crate::types::*;
 use crate::basetraits::*;
 use crate::action::*;
-use crate::damage::DamageKind;
-use crate::dice::DiceExpr;
+use crate::damage::{DamageKind, DamageDefenses};
+use crate::dice::{Die, DiceExpr};
+use crate::meta::Metadata;
+use crate::explain::Derivation;
+use crate::citation::*;
+use crate::combat::{CombatPair, CombatSettings};
+use crate::check::Advantage;
+use crate::modifier::*;
+use crate::util;
 
-use std::collections::HashSet;
-use std::rc::Rc;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Encounter-design roles, as inferred from a creature's stats and actions.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Role {
+    /// Melee, and durable through hit points rather than armor.
+    Brute,
+    /// Mostly ranged attacks.
+    Artillery,
+    /// Denies actions or affects areas.
+    Controller,
+    /// Finesse fighters and those mixing melee and ranged attacks.
+    Skirmisher,
+    /// No damaging actions of its own.
+    Support,
+}
 
 /// A basic creature, without CR or prof bonus, as that takes nontrivial effort to compute.
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BaseCreature {
     pub ascores: AScores,
     pub ac_kind: ACKind,
     pub actions: Vec<Action>,
     pub size: Size,
+    pub speeds: Speeds,
+    pub alignment: Alignment,
+    pub creature_type: CreatureType,
+    /// Subtypes of the creature type, e.g. "goblinoid" (5e MM, p. 6).
+    pub subtypes: HashSet<String>,
     pub hit_dice: usize,
     pub immunities: HashSet<DamageKind>,
     pub resistances: HashSet<DamageKind>,
     pub vulnerabilities: HashSet<DamageKind>,
+    /// Flat reductions to damage, applied before resistances (e.g. Heavy Armor Master).
+    pub damage_reduction: HashMap<DamageKind, usize>,
+    /// Rerolls natural 1s on attack rolls, checks, and saves once (Halfling Luck).
+    pub lucky: bool,
+    /// Proficiency in saving throws, by ability; unlisted abilities have none.
+    pub save_proficiencies: HashMap<Ability, ProficiencyLevel>,
+    /// Proficiency in skills; unlisted skills have `unlisted_skill_proficiency`.
+    pub skill_proficiencies: HashMap<Skill, ProficiencyLevel>,
+    /// Proficiency in skills not in `skill_proficiencies` (Half for Jack of All Trades).
+    pub unlisted_skill_proficiency: ProficiencyLevel,
+    /// Bonuses to initiative besides the Dex modifier (e.g. +5 for Alert).
+    pub initiative_bonus: isize,
+    /// Data attached by other tools, which this crate ignores.
+    pub metadata: Metadata,
 }
 
 impl BaseCreature {
-    /// 5e PHB, p. 197 (resistance and vulnerability)
+    /// 5e PHB, p. 197 (resistance and vulnerability). This is a multiplier summary which ignores
+    /// rounding and flat reductions; `damage::mitigate` applies damage exactly.
     pub fn damage_factor(&self, k: DamageKind) -> f64 {
         let mut fac = 1.0f64;
         if self.immunities.contains(&k) {
@@ -40,38 +83,310 @@ impl BaseCreature {
         (&self.ascores).into()
     }
 
+    /// The die this creature rolls for attack rolls, ability checks, and saving throws.
+    pub fn d20(&self) -> DiceExpr {
+        if self.lucky {
+            DiceExpr::lucky_d20()
+        } else {
+            DiceExpr::Die(Die::d20())
+        }
+    }
+
     pub fn armor_class(&self) -> AC {
         self.ac_kind.armor_class(&self.mods())
     }
 
-    pub fn expected_hit_points(&self) -> HP {
+    /// The hit point expression, e.g. 4d8+4 for a Medium creature with four hit dice and +1 Con
+    /// (5e DMG, p. 276).
+    pub fn hit_dice_expr(&self) -> DiceExpr {
         use DiceExpr::*;
-        HP(
-            (Times(self.hit_dice, Rc::new(
-                Plus(
-                    Rc::new(Die(self.size.hit_die())),
-                    Rc::new(Const(self.mods().0.con as isize)),
-                )
-            ))).expected() as usize
-        )
+        Times(self.hit_dice, Arc::new(
+            Plus(
+                Arc::new(Die(self.size.hit_die())),
+                Arc::new(Const(self.mods().0.con)),
+            )
+        ))
+    }
+
+    pub fn expected_hit_points(&self) -> HP {
+        HP(self.hit_dice_expr().expected() as usize)
+    }
+
+    /// The least and greatest hit points the hit dice can produce; listed hit points outside this
+    /// range don't match the stat block.
+    pub fn hit_point_range(&self) -> (HP, HP) {
+        let ex = self.hit_dice_expr();
+        (HP(util::clamp_isize(ex.min())), HP(util::clamp_isize(ex.max())))
+    }
+
+    /// All attacks this creature can make, including those within multiattacks.
+    pub fn attacks(&self) -> impl Iterator<Item = &Attack> {
+        self.actions.iter().flat_map(|act| act.kind.attacks())
+    }
+
+    /// Infer encounter-design roles. These are heuristics; a creature may have several roles or
+    /// none.
+    pub fn roles(&self) -> HashSet<Role> {
+        let mut roles = HashSet::new();
+        let (mut melee, mut ranged, mut finesse, mut damaging) = (0, 0, false, false);
+        for atk in self.attacks() {
+            match atk.kind {
+                AttackKind::Melee => melee += 1,
+                AttackKind::Ranged => ranged += 1,
+                AttackKind::Special => (),
+            }
+            finesse |= atk.finesse;
+            damaging |= !atk.dmg_rolls.is_empty() || atk.dmg_bonus > 0;
+            if let Target::Area(_) = atk.target {
+                roles.insert(Role::Controller);
+            }
+            if let Some(Save(_, _, SaveEffect::DeniesActions(_))) = atk.save {
+                roles.insert(Role::Controller);
+            }
+        }
+        if !damaging {
+            roles.insert(Role::Support);
+        }
+        if ranged > melee {
+            roles.insert(Role::Artillery);
+        }
+        if finesse || (melee > 0 && ranged > 0) {
+            roles.insert(Role::Skirmisher);
+        }
+        let hp = self.expected_hit_points();
+        if melee > 0 && self.armor_class() < AC::from(CR::from(hp)) {
+            roles.insert(Role::Brute);
+        }
+        roles
+    }
+
+    /// The multiplier to hit points for resistances and immunities at a given CR (5e DMG, p. 277).
+    /// These only count against bludgeoning, piercing, or slashing damage, as resistance to rarer
+    /// kinds is seldom significant.
+    fn effective_hp_factor(&self, cr: CR) -> f64 {
+        use DamageKind::*;
+        let physical = [Bludgeoning, Piercing, Slashing];
+        let (immune, resistant) = (
+            physical.iter().any(|k| self.immunities.contains(k)),
+            physical.iter().any(|k| self.resistances.contains(k)),
+        );
+        let crf: f64 = cr.into();
+        let (res, imm) = match crf {
+            x if x <= 4.0 => (2.0, 2.0),
+            x if x <= 10.0 => (1.5, 2.0),
+            x if x <= 16.0 => (1.25, 1.5),
+            _ => (1.0, 1.25),
+        };
+        if immune {
+            imm
+        } else if resistant {
+            res
+        } else {
+            1.0
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn defensive_steps(&self) -> DefensiveSteps {
+        let hp = self.expected_hit_points();
+        let factor = self.effective_hp_factor(CR::from(hp));
+        let hp_cr = CR::from(HP((hp.0 as f64 * factor) as usize));
+        let flying = self.speeds.fly > 0 && hp_cr <= CR::CR10
+            && self.attacks().any(|atk| matches!(atk.kind, AttackKind::Ranged));
+        let ac = AC(self.armor_class().0 + if flying { 2 } else { 0 });
+        let expected_ac = AC::from(hp_cr);
+        let cr = step_cr(hp_cr, (ac.0 as isize - expected_ac.0 as isize) / 2);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(hp = hp.0, factor, hp_cr = ?hp_cr, ac = ac.0, expected_ac = expected_ac.0, "defensive CR");
+        DefensiveSteps { hp, factor, hp_cr, ac, expected_ac, cr }
+    }
+
+    /// The defensive challenge rating: that of the effective hit points, moved one step for
+    /// every two points of AC above or below that expected of it (5e DMG, p. 274). Flying
+    /// creatures with ranged attacks count 2 more AC up to CR 10 (5e DMG, p. 279).
+    pub fn defensive_cr(&self) -> CR {
+        self.defensive_steps().cr
+    }
+
+    /// As `defensive_cr`, with its derivation.
+    pub fn explain_defensive_cr(&self) -> Derivation {
+        let st = self.defensive_steps();
+        Derivation::new("defensive CR", st.cr.into()).cite(Citation::dmg(274))
+            .with(Derivation::new("CR of effective hit points", st.hp_cr.into())
+                .with(Derivation::new("expected hit points", st.hp.0 as f64))
+                .with(Derivation::new("hit point multiplier for defenses", st.factor).cite(Citation::dmg(277))))
+            .with(Derivation::new("effective AC", st.ac.0 as f64))
+            .with(Derivation::new("expected AC for the CR", st.expected_ac.0 as f64))
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn offensive_steps(&self, settings: &CombatSettings) -> OffensiveSteps {
+        let attacker = self.clone().with_cr(self.defensive_cr());
+        let defender = Creature::reference(attacker.cr);
+        let pair = CombatPair::new(&attacker, &defender, settings).with_baseline();
+        let rounds = settings.rounds.max(1) as f64;
+        let damage = pair.expected_total_damage() / rounds;
+        let damage_cr = CR::for_expected_damage(damage as usize);
+        let best = self.attacks().max_by(|a, b| pair.expected_damage(a).total_cmp(&pair.expected_damage(b)));
+        let modifier = best.map(|atk| match &atk.save {
+            Some(Save(_, dc, _)) => (
+                "save DC",
+                dc.def_class(&attacker.mods(), attacker.prof_bonus()) as isize,
+                damage_cr.save_dc(),
+            ),
+            None => ("attack bonus", pair.attack_modifier(atk), damage_cr.to_hit_bonus()),
+        });
+        let diff = modifier.map_or(0, |(_, actual, expected)| actual - expected);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(damage, damage_cr = ?damage_cr, modifier = ?modifier, "offensive CR");
+        OffensiveSteps { damage, damage_cr, modifier, cr: step_cr(damage_cr, diff / 2) }
+    }
+
+    /// The offensive challenge rating: that of the damage per round over
+    /// `CombatSettings::rounds`, moved one step for every two points of attack bonus (or save
+    /// DC, if its most damaging attack has a save) above or below that expected of it (5e DMG,
+    /// p. 274). Proficiency is presumed from `defensive_cr`, and damage is against
+    /// `Creature::reference` at that CR as a baseline (see `CombatPair::with_baseline`), so saves
+    /// always fail and denied rounds are worth that CR's damage per round.
+    pub fn offensive_cr(&self, settings: &CombatSettings) -> CR {
+        self.offensive_steps(settings).cr
+    }
+
+    /// As `offensive_cr`, with its derivation.
+    pub fn explain_offensive_cr(&self, settings: &CombatSettings) -> Derivation {
+        let st = self.offensive_steps(settings);
+        let d = Derivation::new("offensive CR", st.cr.into()).cite(Citation::dmg(274))
+            .with(Derivation::new("CR of damage per round", st.damage_cr.into())
+                .with(Derivation::new("expected damage per round", st.damage)
+                    .cite(Citation::dmg(278))));
+        match st.modifier {
+            None => d,
+            Some((what, actual, expected)) => d
+                .with(Derivation::new(what, actual as f64))
+                .with(Derivation::new(format!("expected {} for the CR", what), expected as f64)),
+        }
+    }
+
+    /// The challenge rating, as the average of `defensive_cr` and `offensive_cr` (5e DMG,
+    /// p. 274); averages of at least 1 round to the nearest whole rating.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn compute_cr(&self, settings: &CombatSettings) -> CR {
+        average_cr(self.defensive_cr(), self.offensive_cr(settings))
+    }
+
+    /// As `compute_cr`, with its derivation.
+    pub fn explain_compute_cr(&self, settings: &CombatSettings) -> Derivation {
+        let (def, off) = (self.explain_defensive_cr(), self.explain_offensive_cr(settings));
+        let cr = average_cr(CR::from(def.value), CR::from(off.value));
+        Derivation::new("challenge rating", cr.into()).cite(Citation::dmg(274))
+            .with(def)
+            .with(off)
+    }
+
+    /// A featureless defender for measuring damage in a vacuum, as the DMG does: it has the AC
+    /// expected at the given CR (5e DMG, p. 274), +0 to every save, and no damage defenses.
+    pub fn reference(cr: CR) -> BaseCreature {
+        BaseCreature {
+            ascores: Default::default(),
+            ac_kind: ACKind::Natural(AC::from(cr).0),
+            actions: Vec::new(),
+            size: Size::Medium,
+            speeds: Speeds::default(),
+            alignment: Alignment::default(),
+            creature_type: CreatureType::Humanoid,
+            subtypes: HashSet::new(),
+            hit_dice: 1,
+            immunities: HashSet::new(),
+            resistances: HashSet::new(),
+            vulnerabilities: HashSet::new(),
+            damage_reduction: HashMap::new(),
+            lucky: false,
+            save_proficiencies: HashMap::new(),
+            skill_proficiencies: HashMap::new(),
+            unlisted_skill_proficiency: ProficiencyLevel::None,
+            initiative_bonus: 0,
+            metadata: Metadata::default(),
+        }
     }
 
     /// Fictitiously make this BaseCreature into a Creature with the given CR. No guarantee is
     /// given as to that value's accuracy, which can have effect (through the proficiency bonus) on
     /// other calculations downstream.
     pub fn with_cr(self, cr: CR) -> Creature {
-        Creature { base: self, cr: cr }
+        Creature { base: self, cr }
+    }
+}
+
+impl DamageDefenses for BaseCreature {
+    fn immune(&self, k: DamageKind) -> bool {
+        self.immunities.contains(&k)
+    }
+
+    fn resistant(&self, k: DamageKind) -> bool {
+        self.resistances.contains(&k)
+    }
+
+    fn vulnerable(&self, k: DamageKind) -> bool {
+        self.vulnerabilities.contains(&k)
+    }
+
+    fn reduction(&self, k: DamageKind) -> usize {
+        self.damage_reduction.get(&k).cloned().unwrap_or(0)
+    }
+}
+
+/// The intermediate values of `BaseCreature::defensive_cr`.
+struct DefensiveSteps {
+    hp: HP,
+    factor: f64,
+    hp_cr: CR,
+    ac: AC,
+    expected_ac: AC,
+    cr: CR,
+}
+
+/// The intermediate values of `BaseCreature::offensive_cr`; `modifier` is what the most damaging
+/// attack uses (attack bonus or save DC), its value, and the value expected at `damage_cr`.
+struct OffensiveSteps {
+    damage: f64,
+    damage_cr: CR,
+    modifier: Option<(&'static str, isize, isize)>,
+    cr: CR,
+}
+
+/// The average of two challenge ratings, rounded to the nearest whole rating at or above 1.
+fn average_cr(a: CR, b: CR) -> CR {
+    let avg = (f64::from(a) + f64::from(b)) / 2.0;
+    if avg < 1.0 {
+        CR::from(avg)
+    } else {
+        CR::from(util::floor(avg + 0.5) as f64)
+    }
+}
+
+/// Move a challenge rating by `steps`, up if positive and down if negative.
+fn step_cr(cr: CR, steps: isize) -> CR {
+    if steps < 0 {
+        cr.step_down(steps.unsigned_abs())
+    } else {
+        cr.step_up(steps as usize)
     }
 }
 
 /// A Creature is a BaseCreature which has a cached CR and proficiency
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Creature {
     base: BaseCreature,
     cr: CR,
 }
 
 impl Creature {
+    /// A featureless defender for measuring damage in a vacuum; see `BaseCreature::reference`.
+    pub fn reference(cr: CR) -> Creature {
+        BaseCreature::reference(cr).with_cr(cr)
+    }
+
     pub fn damage_factor(&self, k: DamageKind) -> f64 {
         self.base.damage_factor(k)
     }
@@ -80,7 +395,134 @@ impl Creature {
         self.base.mods()
     }
 
+    pub fn d20(&self) -> DiceExpr {
+        self.base.d20()
+    }
+
     pub fn prof_bonus(&self) -> ProfBonus {
         self.cr.into()
     }
+
+    pub fn cr(&self) -> CR {
+        self.cr
+    }
+
+    pub fn expected_hit_points(&self) -> HP {
+        self.base.expected_hit_points()
+    }
+
+    pub fn speeds(&self) -> &Speeds {
+        &self.base.speeds
+    }
+
+    pub fn roles(&self) -> HashSet<Role> {
+        self.base.roles()
+    }
+
+    pub fn armor_class(&self) -> AC {
+        self.base.armor_class()
+    }
+
+    pub fn size(&self) -> Size {
+        self.base.size
+    }
+
+    /// The modifier to saving throws with an ability, adding the proficiency bonus per its
+    /// proficiency (e.g. "Saving Throws: Dex +5").
+    pub fn save_modifier(&self, ab: Ability) -> isize {
+        let mut stack = SaveKind::Ability(ab).modifier_stack(&self.mods());
+        let level = self.base.save_proficiencies.get(&ab).cloned().unwrap_or_default();
+        if level != ProficiencyLevel::None {
+            stack.push(Modifier::new(ModifierSource::Proficiency, level.bonus(self.prof_bonus())));
+        }
+        stack.total()
+    }
+
+    /// The modifier to checks with a skill: the governing ability's modifier, plus the
+    /// proficiency bonus per its proficiency (e.g. Stealth +6).
+    pub fn skill_modifier(&self, skill: Skill) -> isize {
+        let level = self.base.skill_proficiencies.get(&skill).cloned()
+            .unwrap_or(self.base.unlisted_skill_proficiency);
+        self.mods().0[skill.ability()] + level.bonus(self.prof_bonus())
+    }
+
+    /// The modifier to initiative, a Dex check (5e PHB, p. 177): the Dex modifier, plus
+    /// `initiative_bonus`, plus `unlisted_skill_proficiency` (as for Jack of All Trades, which
+    /// covers every check without proficiency).
+    pub fn initiative_modifier(&self) -> isize {
+        self.mods().0.dex + self.base.initiative_bonus
+            + self.base.unlisted_skill_proficiency.bonus(self.prof_bonus())
+    }
+
+    pub fn ascores(&self) -> &AScores {
+        &self.base.ascores
+    }
+
+    /// The score of a passive check with the given skill, 10 plus its modifier (5e PHB, p. 175).
+    pub fn passive_score(&self, skill: Skill, adv: Advantage) -> isize {
+        10 + self.skill_modifier(skill) + adv.passive_bonus()
+    }
+
+    pub fn creature_type(&self) -> CreatureType {
+        self.base.creature_type
+    }
+
+    pub fn has_subtype(&self, subtype: &str) -> bool {
+        self.base.subtypes.contains(subtype)
+    }
+
+    pub fn actions(&self) -> &[Action] {
+        &self.base.actions
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.base.metadata
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.base.metadata
+    }
+}
+
+impl DamageDefenses for Creature {
+    fn immune(&self, k: DamageKind) -> bool {
+        self.base.immune(k)
+    }
+
+    fn resistant(&self, k: DamageKind) -> bool {
+        self.base.resistant(k)
+    }
+
+    fn vulnerable(&self, k: DamageKind) -> bool {
+        self.base.vulnerable(k)
+    }
+
+    fn reduction(&self, k: DamageKind) -> usize {
+        self.base.reduction(k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trip() {
+        use super::*;
+        use crate::meta::MetaValue;
+
+        let mut base = BaseCreature::reference(CR::CR1);
+        base.resistances.insert(DamageKind::Fire);
+        base.save_proficiencies.insert(Ability::Dex, ProficiencyLevel::Proficient);
+        base.actions.push(Action::parse("Bite", "melee; hit +4; on hit: 1d8+2 piercing + prone").unwrap());
+        let mut creature = base.with_cr(CR::CR1);
+        creature.metadata_mut().insert("owner", MetaValue::Str("gm".into()));
+        creature.metadata_mut().tag("homebrew");
+        let json = serde_json::to_string(&creature).unwrap();
+        let back: Creature = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.metadata(), creature.metadata());
+        assert_eq!(back.cr(), CR::CR1);
+        assert_eq!(back.base.resistances, creature.base.resistances);
+        assert_eq!(back.base.save_proficiencies, creature.base.save_proficiencies);
+        assert_eq!(back.actions()[0].kind.attacks().next(), creature.actions()[0].kind.attacks().next());
+    }
 }
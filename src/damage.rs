@@ -1,5 +1,15 @@
+use crate::basetraits::HP;
+use crate::citation::*;
+use crate::dice::{DiceExpr, Die};
+
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+
+use rand::Rng;
+
 /// 5e PHB, p. 196
 #[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DamageKind {
     Acid,
     Bludgeoning,
@@ -16,4 +26,260 @@ pub enum DamageKind {
     Thunder,
 }
 
+impl Cited for DamageKind {
+    const CITATION: Citation = Citation::phb(196);
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
 pub struct Damage(pub usize, pub DamageKind);
+
+/// A creature's defenses against kinds of damage (5e PHB, p. 197).
+pub trait DamageDefenses {
+    fn immune(&self, k: DamageKind) -> bool;
+    fn resistant(&self, k: DamageKind) -> bool;
+    fn vulnerable(&self, k: DamageKind) -> bool;
+
+    /// A flat reduction to damage of this kind (e.g. the Heavy Armor Master feat).
+    fn reduction(&self, _k: DamageKind) -> usize {
+        0
+    }
+}
+
+/// The damage that gets through a creature's defenses. Per 5e PHB, p. 197, bonuses and
+/// penalties (here, flat reductions) apply first, then resistance (halving, rounded down), then
+/// vulnerability; immunity negates it entirely. Resistance and vulnerability together don't
+/// cancel: 5 damage becomes 2, then 4.
+pub fn mitigate<D: DamageDefenses + ?Sized>(defenses: &D, dmg: Damage) -> usize {
+    let Damage(amt, k) = dmg;
+    if defenses.immune(k) {
+        return 0;
+    }
+    let mut amt = amt.saturating_sub(defenses.reduction(k));
+    if defenses.resistant(k) {
+        amt /= 2;
+    }
+    if defenses.vulnerable(k) {
+        amt *= 2;
+    }
+    amt
+}
+
+/// All of the damage dealt by a single source (one attack, one creature's share of an area
+/// effect), possibly of several kinds. Each kind is totalled before defenses apply, so halving
+/// rounds once per kind rather than once per roll, and the whole instance prompts one
+/// concentration save; separate sources are separate instances (5e PHB, p. 203).
+#[derive(Debug,Clone,PartialEq,Eq,Default)]
+pub struct DamageInstance(pub Vec<Damage>);
+
+impl DamageInstance {
+    /// The total of each kind of damage, in order of first appearance.
+    pub fn by_kind(&self) -> Vec<Damage> {
+        let mut totals: Vec<Damage> = Vec::new();
+        for Damage(amt, k) in &self.0 {
+            match totals.iter_mut().find(|Damage(_, tk)| tk == k) {
+                Some(Damage(tamt, _)) => *tamt += amt,
+                None => totals.push(Damage(*amt, *k)),
+            }
+        }
+        totals
+    }
+
+    /// The damage that gets through a creature's defenses; see `mitigate`.
+    pub fn mitigate<D: DamageDefenses + ?Sized>(&self, defenses: &D) -> usize {
+        self.by_kind().into_iter().map(|dmg| mitigate(defenses, dmg)).sum()
+    }
+}
+
+/// A creature's hit points, including temporary hit points (5e PHB, p. 198).
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct HitPoints {
+    pub current: HP,
+    pub temporary: HP,
+}
+
+/// The outcome of applying one instance of damage.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub struct DamageTaken {
+    /// Damage remaining after defenses.
+    pub mitigated: usize,
+    /// Of that, the amount absorbed by temporary hit points.
+    pub absorbed: usize,
+    /// Of that, the amount taken from hit points (which don't go below zero).
+    pub lost: usize,
+}
+
+impl DamageTaken {
+    /// The DC of the Constitution save to maintain concentration, if any damage was taken: 10
+    /// or half the damage, whichever is higher (5e PHB, p. 203). Damage absorbed by temporary
+    /// hit points still counts.
+    pub fn concentration_dc(&self) -> Option<usize> {
+        if self.mitigated == 0 {
+            None
+        } else {
+            Some(max(10, self.mitigated / 2))
+        }
+    }
+
+    /// Whether this is massive damage for a creature with the given hit point maximum: at least
+    /// half of it from a single source (an optional rule; 5e DMG, p. 273). The creature must then
+    /// pass a DC `MASSIVE_DAMAGE_DC` Constitution save or roll on `SystemShock`.
+    pub fn is_massive(&self, max_hp: HP) -> bool {
+        self.mitigated > 0 && 2 * self.mitigated >= max_hp.0
+    }
+}
+
+/// The Constitution save DC to avoid system shock from massive damage (5e DMG, p. 273).
+pub const MASSIVE_DAMAGE_DC: usize = 15;
+
+/// The System Shock table, rolled on a d10 after failing a save against massive damage (5e DMG,
+/// p. 273). The lasting effects end at the end of the creature's next turn.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum SystemShock {
+    /// Drops to 0 hit points.
+    Dying,
+    /// Drops to 0 hit points, but is stable.
+    Stable,
+    Stunned,
+    /// Can't take reactions, and has disadvantage on attack rolls and ability checks.
+    Reeling,
+    /// Can't take reactions.
+    NoReactions,
+}
+
+impl Cited for SystemShock {
+    const CITATION: Citation = Citation::dmg(273);
+}
+
+impl SystemShock {
+    /// The entry for a d10 roll.
+    pub fn from_roll(roll: isize) -> SystemShock {
+        match roll {
+            x if x <= 1 => SystemShock::Dying,
+            2 | 3 => SystemShock::Stable,
+            4 | 5 => SystemShock::Stunned,
+            6 | 7 => SystemShock::Reeling,
+            _ => SystemShock::NoReactions,
+        }
+    }
+}
+
+impl HitPoints {
+    /// Apply damage: it is mitigated by defenses (see `mitigate`), then depletes temporary hit
+    /// points before hit points (5e PHB, p. 198).
+    pub fn apply_damage<D: DamageDefenses + ?Sized>(&mut self, defenses: &D, dmg: Damage) -> DamageTaken {
+        self.take(mitigate(defenses, dmg))
+    }
+
+    /// Apply all of the damage from a single source at once; see `DamageInstance`.
+    pub fn apply_instance<D: DamageDefenses + ?Sized>(&mut self, defenses: &D, inst: &DamageInstance) -> DamageTaken {
+        self.take(inst.mitigate(defenses))
+    }
+
+    /// Apply the massive damage rule to damage just taken, for a creature with the given hit
+    /// point maximum which rolls `d20` plus `con_save` for Constitution saves: if the damage is
+    /// massive (see `DamageTaken::is_massive`) and the save against `MASSIVE_DAMAGE_DC` fails,
+    /// the creature rolls on `SystemShock`, dropping to 0 hit points on `Dying` or `Stable`.
+    pub fn apply_massive_damage<R: Rng>(
+        &mut self, taken: &DamageTaken, max_hp: HP, d20: &DiceExpr, con_save: isize, rng: &mut R,
+    ) -> Option<SystemShock> {
+        if !taken.is_massive(max_hp) || d20.roll_value(rng) + con_save >= MASSIVE_DAMAGE_DC as isize {
+            return None;
+        }
+        let shock = SystemShock::from_roll(DiceExpr::Die(Die::d10()).roll_value(rng));
+        if let SystemShock::Dying | SystemShock::Stable = shock {
+            self.current = HP(0);
+        }
+        Some(shock)
+    }
+
+    fn take(&mut self, mitigated: usize) -> DamageTaken {
+        let absorbed = min(mitigated, self.temporary.0);
+        self.temporary.0 -= absorbed;
+        let lost = min(mitigated - absorbed, self.current.0);
+        self.current.0 -= lost;
+        DamageTaken { mitigated, absorbed, lost }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[derive(Default)]
+    struct Defenses {
+        immune: bool,
+        resistant: bool,
+        vulnerable: bool,
+        reduction: usize,
+    }
+
+    impl DamageDefenses for Defenses {
+        fn immune(&self, _k: DamageKind) -> bool { self.immune }
+        fn resistant(&self, _k: DamageKind) -> bool { self.resistant }
+        fn vulnerable(&self, _k: DamageKind) -> bool { self.vulnerable }
+        fn reduction(&self, _k: DamageKind) -> usize { self.reduction }
+    }
+
+    fn fire(amt: usize) -> Damage {
+        Damage(amt, DamageKind::Fire)
+    }
+
+    #[test]
+    fn mitigation_order() {
+        assert_eq!(mitigate(&Defenses::default(), fire(7)), 7);
+        assert_eq!(mitigate(&Defenses { immune: true, vulnerable: true, ..Default::default() }, fire(7)), 0);
+        assert_eq!(mitigate(&Defenses { resistant: true, ..Default::default() }, fire(7)), 3);
+        assert_eq!(mitigate(&Defenses { vulnerable: true, ..Default::default() }, fire(7)), 14);
+        assert_eq!(mitigate(&Defenses { resistant: true, vulnerable: true, ..Default::default() }, fire(5)), 4);
+        assert_eq!(mitigate(&Defenses { reduction: 3, resistant: true, ..Default::default() }, fire(10)), 3);
+        assert_eq!(mitigate(&Defenses { reduction: 3, vulnerable: true, ..Default::default() }, fire(10)), 14);
+        assert_eq!(mitigate(&Defenses { reduction: 3, ..Default::default() }, fire(2)), 0);
+    }
+
+    #[test]
+    fn temporary_hit_points() {
+        let mut hp = HitPoints { current: HP(10), temporary: HP(5) };
+        let taken = hp.apply_damage(&Defenses { resistant: true, ..Default::default() }, fire(16));
+        assert_eq!(taken, DamageTaken { mitigated: 8, absorbed: 5, lost: 3 });
+        assert_eq!(hp, HitPoints { current: HP(7), temporary: HP(0) });
+        let taken = hp.apply_damage(&Defenses::default(), fire(20));
+        assert_eq!(taken, DamageTaken { mitigated: 20, absorbed: 0, lost: 7 });
+        assert_eq!(hp.current, HP(0));
+    }
+
+    #[test]
+    fn instances() {
+        let resistant = Defenses { resistant: true, ..Default::default() };
+        let inst = DamageInstance(vec![fire(3), Damage(4, DamageKind::Slashing), fire(3)]);
+        // Fire is totalled before halving: (3 + 3) / 2 + 4 / 2, not 3 / 2 + 3 / 2 + 4 / 2.
+        assert_eq!(inst.mitigate(&resistant), 5);
+        let mut hp = HitPoints { current: HP(30), temporary: HP(0) };
+        let taken = hp.apply_instance(&Defenses::default(), &DamageInstance(vec![fire(24), fire(2)]));
+        assert_eq!(taken.concentration_dc(), Some(13));
+        let taken = hp.apply_instance(&Defenses::default(), &DamageInstance(vec![fire(3)]));
+        assert_eq!(taken.concentration_dc(), Some(10));
+        let taken = hp.apply_damage(&Defenses { immune: true, ..Default::default() }, fire(3));
+        assert_eq!(taken.concentration_dc(), None);
+    }
+
+    #[test]
+    fn massive_damage() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let (fail, pass) = (DiceExpr::Const(1), DiceExpr::Const(20));
+        let mut hp = HitPoints { current: HP(40), temporary: HP(0) };
+        let taken = hp.apply_damage(&Defenses::default(), fire(19));
+        assert_eq!(hp.apply_massive_damage(&taken, HP(40), &fail, 0, &mut rng), None);
+        let taken = hp.apply_damage(&Defenses::default(), fire(20));
+        assert_eq!(hp.apply_massive_damage(&taken, HP(40), &pass, 0, &mut rng), None);
+        assert_eq!(hp.current, HP(1));
+        let mut hp = HitPoints { current: HP(40), temporary: HP(0) };
+        let taken = hp.apply_damage(&Defenses::default(), fire(20));
+        match hp.apply_massive_damage(&taken, HP(40), &fail, 0, &mut rng) {
+            Some(SystemShock::Dying) | Some(SystemShock::Stable) => assert_eq!(hp.current, HP(0)),
+            Some(_) => assert_eq!(hp.current, HP(20)),
+            None => panic!("a failed save against massive damage causes system shock"),
+        }
+    }
+}
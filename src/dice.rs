@@ -1,57 +1,608 @@
 use crate::types::*;
+use crate::util;
+use crate::parse::{self, ParseError};
 
-use std::rc::*;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::convert::TryFrom;
+use core::str::FromStr;
 
 use rand::Rng;
 
 /// The primitive type used to represent a die value.
 pub type Value = isize;
 
-/// Represents an n-sided die
+/// Represents an n-sided die, which has at least one side (see `Die::new`).
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "Value", into = "Value"))]
+pub struct Die(Value);
+
+/// A die with fewer than one side.
 #[derive(Debug,Clone,Copy,PartialEq,Eq)]
-pub struct Die(pub Value);
+pub struct InvalidDie(pub Value);
 
-/// An arbitrary expression of dice. No guarantee is given as to its structure.
-#[derive(Debug,Clone)]
+impl fmt::Display for InvalidDie {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a die must have at least one side, not {}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidDie {}
+
+impl Die {
+    pub fn new(sides: Value) -> Result<Die, InvalidDie> {
+        if sides > 0 {
+            Ok(Die(sides))
+        } else {
+            Err(InvalidDie(sides))
+        }
+    }
+
+    pub const fn sides(self) -> Value {
+        self.0
+    }
+
+    pub const fn d4() -> Die { Die(4) }
+    pub const fn d6() -> Die { Die(6) }
+    pub const fn d8() -> Die { Die(8) }
+    pub const fn d10() -> Die { Die(10) }
+    pub const fn d12() -> Die { Die(12) }
+    pub const fn d20() -> Die { Die(20) }
+    pub const fn d100() -> Die { Die(100) }
+}
+
+impl TryFrom<Value> for Die {
+    type Error = InvalidDie;
+
+    fn try_from(sides: Value) -> Result<Die, InvalidDie> {
+        Die::new(sides)
+    }
+}
+
+impl From<Die> for Value {
+    fn from(d: Die) -> Value {
+        d.0
+    }
+}
+
+/// The faces of a custom die, of which there is at least one (see `Faces::new`).
+#[derive(Debug,Clone,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "Vec<Value>", into = "Vec<Value>"))]
+pub struct Faces(Arc<Vec<Value>>);
+
+/// A custom die without any faces.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct NoFaces;
+
+impl fmt::Display for NoFaces {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a die must have at least one face")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NoFaces {}
+
+impl Faces {
+    pub fn new(faces: Vec<Value>) -> Result<Faces, NoFaces> {
+        if faces.is_empty() {
+            Err(NoFaces)
+        } else {
+            Ok(Faces(Arc::new(faces)))
+        }
+    }
+}
+
+impl core::ops::Deref for Faces {
+    type Target = [Value];
+
+    fn deref(&self) -> &[Value] {
+        &self.0
+    }
+}
+
+impl TryFrom<Vec<Value>> for Faces {
+    type Error = NoFaces;
+
+    fn try_from(faces: Vec<Value>) -> Result<Faces, NoFaces> {
+        Faces::new(faces)
+    }
+}
+
+impl From<Faces> for Vec<Value> {
+    fn from(fs: Faces) -> Vec<Value> {
+        (*fs.0).clone()
+    }
+}
+
+/// The dice of a standard set, for where only these make sense (hit dice, recharge dice, and
+/// the like).
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum StandardDie {
+    D4, D6, D8, D10, D12, D20, D100,
+}
+
+impl StandardDie {
+    pub const ALL: [StandardDie; 7] = {
+        use StandardDie::*;
+        [D4, D6, D8, D10, D12, D20, D100]
+    };
+
+    pub fn sides(self) -> Value {
+        match self {
+            StandardDie::D4 => 4,
+            StandardDie::D6 => 6,
+            StandardDie::D8 => 8,
+            StandardDie::D10 => 10,
+            StandardDie::D12 => 12,
+            StandardDie::D20 => 20,
+            StandardDie::D100 => 100,
+        }
+    }
+}
+
+impl From<StandardDie> for Die {
+    fn from(sd: StandardDie) -> Die {
+        Die(sd.sides())
+    }
+}
+
+impl TryFrom<Die> for StandardDie {
+    type Error = Die;
+
+    /// The standard die with as many sides, or the die back if there is none.
+    fn try_from(d: Die) -> Result<StandardDie, Die> {
+        StandardDie::ALL.iter().cloned().find(|sd| sd.sides() == d.0).ok_or(d)
+    }
+}
+
+/// An arbitrary expression of dice. No guarantee is given as to its structure; equality and
+/// hashing are structural, so `2d6` and `1d6+1d6` differ (see `simplify`).
+#[derive(Debug,Clone,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DiceExpr {
     Die(Die),
-    Times(usize, Rc<DiceExpr>),
-    Plus(Rc<DiceExpr>, Rc<DiceExpr>),
+    Times(usize, Arc<DiceExpr>),
+    Plus(Arc<DiceExpr>, Arc<DiceExpr>),
     Const(Value),
+    /// A Fudge/Fate die (`dF`), with faces -1, 0, and +1 (two of each).
+    Fudge,
+    /// Percentile dice (`d%`): a tens die (00-90) and a ones die (0-9), read together, with 00
+    /// and 0 reading as 100.
+    Percentile,
+    /// A die with the given faces, each equally likely (e.g. `d{0,0,0,1,1,2}`); repeating a face
+    /// weights it, as for a loaded die.
+    Faces(Faces),
+    /// Roll the expression n times, keeping the highest k results (e.g. `2d20kh1`).
+    KeepHighest(usize, usize, Arc<DiceExpr>),
+    /// Roll the expression n times, keeping the lowest k results (e.g. `2d20kl1`).
+    KeepLowest(usize, usize, Arc<DiceExpr>),
+    /// Reroll any result at or below the threshold (e.g. Great Weapon Fighting is
+    /// `RerollBelow(2, true, d6)` per damage die). If `once`, the second roll stands; otherwise,
+    /// reroll until the result is above the threshold (unless it never can be).
+    RerollBelow(Value, bool, Arc<DiceExpr>),
+    /// Treat any result below the minimum as the minimum (e.g. Reliable Talent is
+    /// `Minimum(10, d20)`).
+    Minimum(Value, Arc<DiceExpr>),
+    /// Count the results at or above the target (e.g. `5d10>=8` for a dice pool). A `Times` pool
+    /// counts each of its rolls; any other expression is a single trial.
+    CountSuccesses(Value, Arc<DiceExpr>),
+    /// The negation of the expression, as in the `1d4` of `2d8-1d4`. Constants are negated
+    /// directly instead (`1d20-2` is `1d20+Const(-2)`).
+    Neg(Arc<DiceExpr>),
+    /// The expression times a constant (e.g. `10d6*10`).
+    Mul(Arc<DiceExpr>, Value),
+    /// The expression divided by a constant, rounded down (e.g. half damage is `DivFloor(x, 2)`;
+    /// 5e PHB, p. 7).
+    DivFloor(Arc<DiceExpr>, Value),
 }
 
 /// The result of rolling a `DiceExpr`, including all intermediate values. This is suitable for
-/// storing a "zero-entropy" copy of this data for posterity.
+/// storing a "zero-entropy" copy of this data for posterity: with the "serde" feature it can be
+/// serialized, and `is_consistent_with` checks a stored roll against its expression.
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DiceRoll {
     Die(Die, Value),
-    Times(usize, Rc<DiceExpr>, Vec<DiceRoll>),
-    Plus(Rc<DiceExpr>, Rc<DiceExpr>, Rc<DiceRoll>, Rc<DiceRoll>),
+    /// The result of a die, or of a whole d20 test such as `2d20kh1`, fixed in advance rather
+    /// than rolled; see `FixedResults`.
+    Fixed(Arc<DiceExpr>, Value),
+    /// A roll of a die or d20 test, then a reroll of the whole of it, whose lower result stands;
+    /// see `Substitution::RerollLower`.
+    RerolledLower(Arc<DiceRoll>, Arc<DiceRoll>),
+    Times(usize, Arc<DiceExpr>, Vec<DiceRoll>),
+    Plus(Arc<DiceExpr>, Arc<DiceExpr>, Arc<DiceRoll>, Arc<DiceRoll>),
     Const(Value),
+    Fudge(Value),
+    /// The tens die (0, 10, ..., 90) and the ones die (0-9).
+    Percentile(Value, Value),
+    Faces(Faces, Value),
+    KeepHighest(usize, usize, Arc<DiceExpr>, Vec<DiceRoll>),
+    KeepLowest(usize, usize, Arc<DiceExpr>, Vec<DiceRoll>),
+    /// Every attempt, in order; the last one stands.
+    RerollBelow(Value, bool, Arc<DiceExpr>, Vec<DiceRoll>),
+    Minimum(Value, Arc<DiceExpr>, Arc<DiceRoll>),
+    CountSuccesses(Value, Arc<DiceExpr>, Arc<DiceRoll>),
+    Neg(Arc<DiceExpr>, Arc<DiceRoll>),
+    Mul(Arc<DiceExpr>, Value, Arc<DiceRoll>),
+    DivFloor(Arc<DiceExpr>, Value, Arc<DiceRoll>),
+}
+
+/// A change committed in advance to the next roll of a die.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Substitution {
+    /// Use this result instead of rolling (a Divination wizard's Portent, 5e PHB, p. 116).
+    Result(Value),
+    /// Roll as usual, then roll once more and keep the lower result (Silvery Barbs). A d20 test
+    /// is rerolled as a whole, so a test with advantage rolls both of its d20s again.
+    RerollLower,
+}
+
+/// Substitutions committed in advance, each consumed by the next roll of its die. A d20 test
+/// rolled with advantage, disadvantage, or rerolls (`2d20kh1`, `d20ro<1`) counts as one roll of
+/// the d20, so a fixed result stands as the test's result rather than as one of its dice.
+#[derive(Debug,Clone,Default)]
+pub struct FixedResults(pub VecDeque<(Die, Substitution)>);
+
+impl FixedResults {
+    /// Commit a result for a later roll of the die.
+    pub fn push(&mut self, die: Die, v: Value) {
+        self.0.push_back((die, Substitution::Result(v)));
+    }
+
+    /// Commit a reroll, keeping the lower result, for a later roll of the die.
+    pub fn push_reroll_lower(&mut self, die: Die) {
+        self.0.push_back((die, Substitution::RerollLower));
+    }
+
+    /// Take the earliest substitution committed for the die, if any.
+    pub fn take(&mut self, die: Die) -> Option<Substitution> {
+        let idx = self.0.iter().position(|(d, _)| *d == die)?;
+        self.0.remove(idx).map(|(_, sub)| sub)
+    }
+}
+
+/// A discrete probability distribution, as (value, probability) pairs in ascending order of value.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Distribution(pub Vec<(Value, f64)>);
+
+impl Distribution {
+    /// The distribution which is always `v`.
+    pub fn point(v: Value) -> Distribution {
+        Distribution(vec![(v, 1.0)])
+    }
+
+    /// Build a distribution from (value, probability) pairs in any order, summing repeats.
+    pub fn from_weights<I: IntoIterator<Item = (Value, f64)>>(pairs: I) -> Distribution {
+        let mut acc: BTreeMap<Value, f64> = BTreeMap::new();
+        for (v, p) in pairs {
+            *acc.entry(v).or_insert(0.0) += p;
+        }
+        Distribution(acc.into_iter().collect())
+    }
+
+    /// The distribution of the sum of independent samples from both distributions.
+    pub fn convolve(&self, other: &Distribution) -> Distribution {
+        Distribution::from_weights(self.0.iter().flat_map(|(va, pa)| {
+            other.0.iter().map(move |(vb, pb)| (va + vb, pa * pb))
+        }))
+    }
+
+    /// The distribution of the sum of n independent samples.
+    pub fn repeat(&self, n: usize) -> Distribution {
+        (0..n).fold(Distribution::point(0), |acc, _| acc.convolve(self))
+    }
+
+    /// The distribution of the sum of the best k of n independent samples, where "best" is
+    /// highest if `highest`, and lowest otherwise. This assigns counts to each value in order of
+    /// preference, so it runs in polynomial time rather than enumerating all n-tuples.
+    pub fn keep(&self, n: usize, k: usize, highest: bool) -> Distribution {
+        let mut order: Vec<(Value, f64)> = self.0.clone();
+        if highest {
+            order.reverse();
+        }
+        // (samples assigned so far, sum kept so far) -> probability
+        let mut states: BTreeMap<(usize, Value), f64> = BTreeMap::new();
+        states.insert((0, 0), 1.0);
+        let last = order.len().saturating_sub(1);
+        for (idx, (v, p)) in order.iter().enumerate() {
+            let mut next = BTreeMap::new();
+            for ((m, s), w) in states {
+                let left = n - m;
+                let counts = if idx == last { left..=left } else { 0..=left };
+                for c in counts {
+                    let kept = c.min(k.saturating_sub(m)) as Value;
+                    *next.entry((m + c, s + kept * v)).or_insert(0.0) +=
+                        w * choose(left, c) * powu(*p, c);
+                }
+            }
+            states = next;
+        }
+        Distribution::from_weights(
+            states.into_iter().filter(|((m, _), _)| *m == n).map(|((_, s), w)| (s, w))
+        )
+    }
+
+    /// Probability of a sample x <= i.
+    pub fn cum_prob(&self, i: Value) -> f64 {
+        self.0.iter().take_while(|(v, _)| *v <= i).map(|(_, p)| p).sum()
+    }
+
+    /// The smallest value v such that P(x <= v) >= p (allowing for rounding error); 0 for an
+    /// empty distribution.
+    pub fn quantile(&self, p: f64) -> Value {
+        let mut cum = 0.0;
+        for (v, pv) in &self.0 {
+            cum += pv;
+            if cum >= p - 1e-9 {
+                return *v;
+            }
+        }
+        self.0.last().map_or(0, |(v, _)| *v)
+    }
+}
+
+impl ExpectedValue for Distribution {
+    fn expected(&self) -> f64 {
+        self.0.iter().map(|(v, p)| (*v as f64) * p).sum()
+    }
+}
+
+/// Roll the expression n times, counting how often each total came up. This is useful for checking
+/// `DiceExpr::distribution` empirically, or for charting.
+pub fn histogram<R: Rng>(ex: &DiceExpr, n: usize, rng: &mut R) -> BTreeMap<Value, usize> {
+    let mut counts = BTreeMap::new();
+    for v in ex.values(rng).take(n) {
+        *counts.entry(v).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// v / d, rounded toward negative infinity (5e always rounds down; 5e PHB, p. 7).
+pub fn div_floor(v: Value, d: Value) -> Value {
+    let q = v / d;
+    if v % d != 0 && ((v < 0) != (d < 0)) {
+        q - 1
+    } else {
+        q
+    }
+}
+
+/// n choose k, as a float.
+fn choose(n: usize, k: usize) -> f64 {
+    (0..k).fold(1.0, |acc, i| acc * ((n - i) as f64) / ((i + 1) as f64))
+}
+
+/// p to the nonnegative integer power c (`f64::powi` requires std).
+fn powu(p: f64, c: usize) -> f64 {
+    (0..c).fold(1.0, |acc, _| acc * p)
 }
 
 impl DiceExpr {
     /// Roll the DiceExpr using the entropy source.
     pub fn roll<R: Rng>(&self, rng: &mut R) -> DiceRoll {
+        self.roll_fixing(rng, &mut FixedResults::default())
+    }
+
+    /// Roll the DiceExpr, applying the substitutions in `fixed` to each roll of their die (or
+    /// d20 test; see `FixedResults`). Those are recorded as `DiceRoll::Fixed` or
+    /// `DiceRoll::RerolledLower`.
+    pub fn roll_fixing<R: Rng>(&self, rng: &mut R, fixed: &mut FixedResults) -> DiceRoll {
+        if let Some(d) = self.test_die() {
+            match fixed.take(d) {
+                Some(Substitution::Result(v)) => return DiceRoll::Fixed(Arc::new(self.clone()), v),
+                Some(Substitution::RerollLower) => {
+                    let first = self.roll_fixing(rng, fixed);
+                    let second = self.roll_fixing(rng, fixed);
+                    return DiceRoll::RerolledLower(Arc::new(first), Arc::new(second));
+                },
+                None => (),
+            }
+        }
         match self {
             DiceExpr::Die(d) => DiceRoll::Die(*d, rng.gen_range(1, d.0 + 1)),
-            DiceExpr::Times(n, ex) => DiceRoll::Times(*n, Rc::clone(ex),
-                (0..*n).map(|_| ex.roll(rng)).collect(),
+            DiceExpr::Times(n, ex) => DiceRoll::Times(*n, Arc::clone(ex),
+                (0..*n).map(|_| ex.roll_fixing(rng, fixed)).collect(),
             ),
             DiceExpr::Plus(xa, xb) => DiceRoll::Plus(
-                Rc::clone(xa), Rc::clone(xb),
-                Rc::new(xa.roll(rng)), Rc::new(xb.roll(rng)),
+                Arc::clone(xa), Arc::clone(xb),
+                Arc::new(xa.roll_fixing(rng, fixed)), Arc::new(xb.roll_fixing(rng, fixed)),
             ),
             DiceExpr::Const(v) => DiceRoll::Const(*v),
+            DiceExpr::Fudge => DiceRoll::Fudge(rng.gen_range(-1, 2)),
+            DiceExpr::Faces(fs) => DiceRoll::Faces(fs.clone(), fs[rng.gen_range(0, fs.len())]),
+            DiceExpr::Percentile => DiceRoll::Percentile(10 * rng.gen_range(0, 10), rng.gen_range(0, 10)),
+            DiceExpr::KeepHighest(n, k, ex) => DiceRoll::KeepHighest(*n, *k, Arc::clone(ex),
+                (0..*n).map(|_| ex.roll_fixing(rng, fixed)).collect(),
+            ),
+            DiceExpr::KeepLowest(n, k, ex) => DiceRoll::KeepLowest(*n, *k, Arc::clone(ex),
+                (0..*n).map(|_| ex.roll_fixing(rng, fixed)).collect(),
+            ),
+            DiceExpr::RerollBelow(t, once, ex) => {
+                let mut drs = vec![ex.roll_fixing(rng, fixed)];
+                if drs[0].value() <= *t && (*once || ex.max() > *t) {
+                    drs.push(ex.roll_fixing(rng, fixed));
+                    while !once && drs[drs.len() - 1].value() <= *t {
+                        drs.push(ex.roll_fixing(rng, fixed));
+                    }
+                }
+                DiceRoll::RerollBelow(*t, *once, Arc::clone(ex), drs)
+            },
+            DiceExpr::Minimum(m, ex) => DiceRoll::Minimum(*m, Arc::clone(ex), Arc::new(ex.roll_fixing(rng, fixed))),
+            DiceExpr::CountSuccesses(t, ex) => DiceRoll::CountSuccesses(*t, Arc::clone(ex),
+                Arc::new(ex.roll_fixing(rng, fixed)),
+            ),
+            DiceExpr::Neg(ex) => DiceRoll::Neg(Arc::clone(ex), Arc::new(ex.roll_fixing(rng, fixed))),
+            DiceExpr::Mul(ex, k) => DiceRoll::Mul(Arc::clone(ex), *k, Arc::new(ex.roll_fixing(rng, fixed))),
+            DiceExpr::DivFloor(ex, d) => DiceRoll::DivFloor(Arc::clone(ex), *d, Arc::new(ex.roll_fixing(rng, fixed))),
+        }
+    }
+
+    /// Roll the DiceExpr, returning only the total. This skips building the `DiceRoll`, so it's
+    /// much cheaper for Monte Carlo loops; only keep nodes allocate.
+    pub fn roll_value<R: Rng>(&self, rng: &mut R) -> Value {
+        match self {
+            DiceExpr::Die(d) => rng.gen_range(1, d.0 + 1),
+            DiceExpr::Times(n, ex) => (0..*n).map(|_| ex.roll_value(rng)).sum(),
+            DiceExpr::Plus(xa, xb) => xa.roll_value(rng) + xb.roll_value(rng),
+            DiceExpr::Const(v) => *v,
+            DiceExpr::Fudge => rng.gen_range(-1, 2),
+            DiceExpr::Faces(fs) => fs[rng.gen_range(0, fs.len())],
+            DiceExpr::Percentile => rng.gen_range(1, 101),
+            DiceExpr::KeepHighest(n, k, ex) | DiceExpr::KeepLowest(n, k, ex) => {
+                let mut vs: Vec<Value> = (0..*n).map(|_| ex.roll_value(rng)).collect();
+                if let DiceExpr::KeepHighest(..) = self {
+                    vs.sort_unstable_by(|a, b| b.cmp(a));
+                } else {
+                    vs.sort_unstable();
+                }
+                vs.iter().take(*k).sum()
+            },
+            DiceExpr::RerollBelow(t, once, ex) => {
+                let mut v = ex.roll_value(rng);
+                if v <= *t && (*once || ex.max() > *t) {
+                    v = ex.roll_value(rng);
+                    while !once && v <= *t {
+                        v = ex.roll_value(rng);
+                    }
+                }
+                v
+            },
+            DiceExpr::Minimum(m, ex) => ex.roll_value(rng).max(*m),
+            DiceExpr::CountSuccesses(t, ex) => {
+                let (n, trial) = ex.trials();
+                (0..n).filter(|_| trial.roll_value(rng) >= *t).count() as Value
+            },
+            DiceExpr::Neg(ex) => -ex.roll_value(rng),
+            DiceExpr::Mul(ex, k) => ex.roll_value(rng) * k,
+            DiceExpr::DivFloor(ex, d) => div_floor(ex.roll_value(rng), *d),
+        }
+    }
+
+    /// An endless iterator of totals from `roll_value`.
+    pub fn values<'a, R: Rng>(&'a self, rng: &'a mut R) -> impl Iterator<Item = Value> + 'a {
+        core::iter::repeat_with(move || self.roll_value(rng))
+    }
+
+    /// The expression rolled on a critical hit: all of its dice are rolled twice, but constants
+    /// are not doubled (5e PHB, p. 196).
+    pub fn crit(&self) -> DiceExpr {
+        match self {
+            DiceExpr::Const(_) => self.clone(),
+            DiceExpr::Plus(xa, xb) => DiceExpr::Plus(Arc::new(xa.crit()), Arc::new(xb.crit())),
+            DiceExpr::Neg(x) => DiceExpr::Neg(Arc::new(x.crit())),
+            DiceExpr::Mul(x, k) => DiceExpr::Mul(Arc::new(x.crit()), *k),
+            DiceExpr::DivFloor(x, d) => DiceExpr::DivFloor(Arc::new(x.crit()), *d),
+            DiceExpr::Times(n, x) if !x.is_atom() => DiceExpr::Times(*n, Arc::new(x.crit())),
+            x => x.clone().times(2),
+        }
+    }
+
+    /// As `crit`, with `extra` more of the expression's first die (e.g. one more weapon damage
+    /// die for Brutal Critical, 5e PHB, p. 49).
+    pub fn crit_with(&self, extra: usize) -> DiceExpr {
+        match self.first_atom() {
+            Some(atom) if extra > 0 => DiceExpr::Plus(
+                Arc::new(self.crit()), Arc::new(atom.clone().times(extra)),
+            ),
+            _ => self.crit(),
+        }
+    }
+
+    /// The first single die (see `is_atom`) in the expression, if any.
+    fn first_atom(&self) -> Option<&DiceExpr> {
+        match self {
+            x if x.is_atom() => Some(x),
+            DiceExpr::Plus(xa, xb) => xa.first_atom().or_else(|| xb.first_atom()),
+            DiceExpr::Times(_, x) | DiceExpr::KeepHighest(_, _, x) | DiceExpr::KeepLowest(_, _, x) |
+            DiceExpr::Minimum(_, x) |
+            DiceExpr::Mul(x, _) | DiceExpr::DivFloor(x, _) => x.first_atom(),
+            _ => None,
+        }
+    }
+
+    /// The negation of this expression, folding constants.
+    pub fn negate(self) -> DiceExpr {
+        match self {
+            DiceExpr::Const(v) => DiceExpr::Const(-v),
+            DiceExpr::Neg(x) => (*x).clone(),
+            x => DiceExpr::Neg(Arc::new(x)),
+        }
+    }
+
+    /// The die of a single roll, seen through advantage, disadvantage, and rerolls (`d20`,
+    /// `2d20kh1`, `d20ro<1`), which a `FixedResults` substitution applies to as a whole.
+    fn test_die(&self) -> Option<Die> {
+        match self {
+            DiceExpr::Die(d) => Some(*d),
+            DiceExpr::KeepHighest(_, 1, x) | DiceExpr::KeepLowest(_, 1, x) |
+            DiceExpr::RerollBelow(_, _, x) => x.test_die(),
+            _ => None,
+        }
+    }
+
+    /// A d20 which rerolls a natural 1 once, keeping the new result (Halfling Luck, 5e PHB, p. 28).
+    pub fn lucky_d20() -> DiceExpr {
+        DiceExpr::RerollBelow(1, true, Arc::new(DiceExpr::Die(Die::d20())))
+    }
+
+    /// The exact distribution of values this expression can roll. This is computed by
+    /// convolution, so its cost grows with the number of distinct totals.
+    pub fn distribution(&self) -> Distribution {
+        match self {
+            DiceExpr::Die(d) => Distribution((1..=d.0).map(|v| (v, 1.0 / (d.0 as f64))).collect()),
+            DiceExpr::Times(n, x) => x.distribution().repeat(*n),
+            DiceExpr::Plus(xa, xb) => xa.distribution().convolve(&xb.distribution()),
+            DiceExpr::Const(v) => Distribution::point(*v),
+            DiceExpr::Fudge => Distribution((-1..=1).map(|v| (v, 1.0 / 3.0)).collect()),
+            DiceExpr::Faces(fs) => Distribution::from_weights(fs.iter().map(|v| (*v, 1.0 / (fs.len() as f64)))),
+            DiceExpr::Percentile => Distribution((1..=100).map(|v| (v, 0.01)).collect()),
+            DiceExpr::KeepHighest(n, k, x) => x.distribution().keep(*n, *k, true),
+            DiceExpr::KeepLowest(n, k, x) => x.distribution().keep(*n, *k, false),
+            DiceExpr::RerollBelow(t, once, x) => {
+                let dist = x.distribution();
+                let low = dist.cum_prob(*t);
+                let high = dist.0.iter().filter(|(v, _)| v > t);
+                if *once {
+                    Distribution::from_weights(
+                        high.cloned().chain(dist.0.iter().map(|(v, p)| (*v, low * p)))
+                    )
+                } else if low < 1.0 {
+                    Distribution(high.map(|(v, p)| (*v, p / (1.0 - low))).collect())
+                } else {
+                    dist
+                }
+            },
+            DiceExpr::Minimum(m, x) =>
+                Distribution::from_weights(x.distribution().0.iter().map(|(v, p)| ((*v).max(*m), *p))),
+            DiceExpr::CountSuccesses(t, x) => {
+                let (n, trial) = x.trials();
+                let p = trial.prob_pass(*t);
+                Distribution((0..=n).map(|k| {
+                    (k as Value, choose(n, k) * powu(p, k) * powu(1.0 - p, n - k))
+                }).collect())
+            },
+            DiceExpr::Neg(x) => Distribution(x.distribution().0.iter().rev().map(|(v, p)| (-v, *p)).collect()),
+            DiceExpr::Mul(x, k) =>
+                Distribution::from_weights(x.distribution().0.iter().map(|(v, p)| (v * k, *p))),
+            DiceExpr::DivFloor(x, d) =>
+                Distribution::from_weights(x.distribution().0.iter().map(|(v, p)| (div_floor(*v, *d), *p))),
         }
     }
 
     /// Cumulative probability--the probability that, given underlying distribution X, the
     /// resulting value gives x <= i. Note that this is a "roll under"; see `prob_pass` below.
     ///
-    /// This implementation intentionally has a number of unimplemented cases due to the general
-    /// intractability of the binomial distribution. Implementations should strive to put as much
-    /// of the calculation into `i` as possible.
+    /// Single dice and constants are computed directly; everything else falls back to the full
+    /// `distribution`, which can be expensive for large expressions. Implementations should
+    /// strive to put as much of the calculation into `i` as possible.
     pub fn cum_prob(&self, i: Value) -> f64 {
         match self {
             DiceExpr::Die(d) => {
@@ -70,7 +621,161 @@ impl DiceExpr {
                     1.0
                 }
             },
-            _ => unimplemented!(),
+            _ => self.distribution().cum_prob(i),
+        }
+    }
+
+    /// The number of independent trials in this expression and the expression for each; a
+    /// `Times` is n trials, while anything else is one.
+    fn trials(&self) -> (usize, &DiceExpr) {
+        match self {
+            DiceExpr::Times(n, x) => (*n, x),
+            x => (1, x),
+        }
+    }
+
+    /// The least value this expression can roll.
+    pub fn min(&self) -> Value {
+        match self {
+            DiceExpr::Die(_) => 1,
+            DiceExpr::Fudge => -1,
+            DiceExpr::Faces(fs) => fs.iter().cloned().min().unwrap_or(0),
+            DiceExpr::Percentile => 1,
+            DiceExpr::Times(n, x) => (*n as Value) * x.min(),
+            DiceExpr::Plus(xa, xb) => xa.min() + xb.min(),
+            DiceExpr::Const(v) => *v,
+            DiceExpr::KeepHighest(n, k, x) | DiceExpr::KeepLowest(n, k, x) =>
+                (*k.min(n) as Value) * x.min(),
+            DiceExpr::RerollBelow(t, once, x) => if *once || x.min() > *t || x.max() <= *t {
+                x.min()
+            } else {
+                self.distribution().0[0].0
+            },
+            DiceExpr::Minimum(m, x) => x.min().max(*m),
+            DiceExpr::CountSuccesses(t, x) => {
+                let (n, trial) = x.trials();
+                if trial.min() >= *t { n as Value } else { 0 }
+            },
+            DiceExpr::Neg(x) => -x.max(),
+            DiceExpr::Mul(x, k) => (x.min() * k).min(x.max() * k),
+            DiceExpr::DivFloor(x, d) => div_floor(x.min(), *d).min(div_floor(x.max(), *d)),
+        }
+    }
+
+    /// The greatest value this expression can roll.
+    pub fn max(&self) -> Value {
+        match self {
+            DiceExpr::Die(d) => d.0,
+            DiceExpr::Fudge => 1,
+            DiceExpr::Faces(fs) => fs.iter().cloned().max().unwrap_or(0),
+            DiceExpr::Percentile => 100,
+            DiceExpr::Times(n, x) => (*n as Value) * x.max(),
+            DiceExpr::Plus(xa, xb) => xa.max() + xb.max(),
+            DiceExpr::Const(v) => *v,
+            DiceExpr::KeepHighest(n, k, x) | DiceExpr::KeepLowest(n, k, x) =>
+                (*k.min(n) as Value) * x.max(),
+            DiceExpr::RerollBelow(_, _, x) => x.max(),
+            DiceExpr::Minimum(m, x) => x.max().max(*m),
+            DiceExpr::CountSuccesses(t, x) => {
+                let (n, trial) = x.trials();
+                if trial.max() >= *t { n as Value } else { 0 }
+            },
+            DiceExpr::Neg(x) => -x.min(),
+            DiceExpr::Mul(x, k) => (x.min() * k).max(x.max() * k),
+            DiceExpr::DivFloor(x, d) => div_floor(x.min(), *d).max(div_floor(x.max(), *d)),
+        }
+    }
+
+    /// The value at quantile p (e.g. 0.9 for the 90th percentile): the smallest value v such that
+    /// P(x <= v) >= p. Computed from the full `distribution`.
+    pub fn quantile(&self, p: f64) -> Value {
+        self.distribution().quantile(p)
+    }
+
+    /// A canonical form of this expression, for display and comparison: constants are folded,
+    /// sums are flattened with identical dice merged (`1d6+1d6` becomes `2d6`) and opposite
+    /// ones cancelled (`1d6-1d6` becomes `0`), plain dice come first in descending order of
+    /// size, other terms follow in order of their notation, and the constant comes last, as in
+    /// `1d8+2d6+5`. Sums which differ only in the order of their terms simplify to equal
+    /// expressions.
+    pub fn simplify(&self) -> DiceExpr {
+        match self {
+            DiceExpr::Plus(..) | DiceExpr::Neg(_) => {
+                let (mut terms, mut constant) = (Vec::new(), 0);
+                self.collect_terms(1, &mut terms, &mut constant);
+                terms.retain(|(n, _)| *n != 0);
+                let mut keyed: Vec<_> = terms.into_iter().map(|(n, x)| (match &x {
+                    DiceExpr::Die(d) => (0, -d.0, String::new()),
+                    x => (1, 0, x.to_string()),
+                }, n, x)).collect();
+                keyed.sort_by(|(ka, _, _), (kb, _, _)| ka.cmp(kb));
+                let mut terms: Vec<DiceExpr> = keyed.into_iter().map(|(_, n, x)| if n < 0 {
+                    x.times(n.unsigned_abs()).negate()
+                } else {
+                    x.times(n as usize)
+                }).collect();
+                if constant != 0 || terms.is_empty() {
+                    terms.push(DiceExpr::Const(constant));
+                }
+                terms.into_iter().fold(None, |acc, t| Some(match acc {
+                    None => t,
+                    Some(acc) => DiceExpr::Plus(Arc::new(acc), Arc::new(t)),
+                })).unwrap()
+            },
+            DiceExpr::Times(n, x) => x.simplify().times(*n),
+            DiceExpr::Mul(x, k) => match x.simplify() {
+                x if *k == 1 => x,
+                DiceExpr::Const(v) => DiceExpr::Const(v * k),
+                x => DiceExpr::Mul(Arc::new(x), *k),
+            },
+            DiceExpr::DivFloor(x, d) => match x.simplify() {
+                x if *d == 1 => x,
+                DiceExpr::Const(v) => DiceExpr::Const(div_floor(v, *d)),
+                x => DiceExpr::DivFloor(Arc::new(x), *d),
+            },
+            DiceExpr::KeepHighest(n, k, x) => DiceExpr::KeepHighest(*n, *k, Arc::new(x.simplify())),
+            DiceExpr::KeepLowest(n, k, x) => DiceExpr::KeepLowest(*n, *k, Arc::new(x.simplify())),
+            DiceExpr::RerollBelow(t, once, x) => DiceExpr::RerollBelow(*t, *once, Arc::new(x.simplify())),
+            DiceExpr::Minimum(m, x) => DiceExpr::Minimum(*m, Arc::new(x.simplify())),
+            DiceExpr::CountSuccesses(t, x) => DiceExpr::CountSuccesses(*t, Arc::new(x.simplify())),
+            DiceExpr::Die(_) | DiceExpr::Const(_) | DiceExpr::Fudge | DiceExpr::Percentile |
+            DiceExpr::Faces(_) => self.clone(),
+        }
+    }
+
+    /// Flatten a sum, with the given sign, into simplified terms, as (signed count, expression)
+    /// pairs with identical expressions merged, and the sum of its constants.
+    fn collect_terms(&self, sign: isize, terms: &mut Vec<(isize, DiceExpr)>, constant: &mut Value) {
+        let (n, x) = match self {
+            DiceExpr::Plus(xa, xb) => {
+                xa.collect_terms(sign, terms, constant);
+                xb.collect_terms(sign, terms, constant);
+                return;
+            },
+            DiceExpr::Neg(x) => return x.collect_terms(-sign, terms, constant),
+            _ => match self.simplify() {
+                DiceExpr::Const(v) => {
+                    *constant += sign * v;
+                    return;
+                },
+                DiceExpr::Times(n, x) => (sign * n as isize, (*x).clone()),
+                x => (sign, x),
+            },
+        };
+        match terms.iter_mut().find(|(_, t)| *t == x) {
+            Some((m, _)) => *m += n,
+            None => terms.push((n, x)),
+        }
+    }
+
+    /// n of this expression, folding trivial counts and constants.
+    fn times(self, n: usize) -> DiceExpr {
+        match (n, self) {
+            (0, _) => DiceExpr::Const(0),
+            (1, x) => x,
+            (n, DiceExpr::Const(v)) => DiceExpr::Const(v * (n as Value)),
+            (n, DiceExpr::Times(m, x)) => DiceExpr::Times(n * m, x),
+            (n, x) => DiceExpr::Times(n, Arc::new(x)),
         }
     }
 
@@ -81,6 +786,42 @@ impl DiceExpr {
     }
 }
 
+impl DiceExpr {
+    /// An `NdX+C` expression of the given die whose stat-block average (see
+    /// `ExpectedValue::expected_statblock`) is the target average, rounded down. This uses as many
+    /// dice as fit within the average, and makes up the rest with the constant, as when deriving
+    /// damage dice from a target damage per round (5e DMG, p. 276). If even one die averages
+    /// more than the target, the largest smaller standard die which fits is used instead (so
+    /// `approximating(2.0, Die::d8())` is `1d4`), or just the constant if none does.
+    pub fn approximating(average: f64, die: Die) -> DiceExpr {
+        let target = util::floor(average);
+        if (die.0 + 1) / 2 > target {
+            return StandardDie::ALL.iter().rev().map(|sd| Die::from(*sd))
+                .find(|d| d.0 < die.0 && (d.0 + 1) / 2 <= target)
+                .map_or(DiceExpr::Const(target), |d| DiceExpr::approximating(average, d));
+        }
+        let n = util::floor(2.0 * average / ((die.0 + 1) as f64)).max(1);
+        let dice = DiceExpr::Die(die).times(n as usize);
+        match target - util::floor(dice.expected()) {
+            0 => dice,
+            c => DiceExpr::Plus(Arc::new(dice), Arc::new(DiceExpr::Const(c))),
+        }
+    }
+
+    /// Parse standard dice notation, such as `"2d6+3"` or `"4d8 + 2d4 + 5"`.
+    pub fn parse(s: &str) -> Result<DiceExpr, ParseError> {
+        parse::complete(s, parse::dice_expr)
+    }
+}
+
+impl FromStr for DiceExpr {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<DiceExpr, ParseError> {
+        DiceExpr::parse(s)
+    }
+}
+
 impl ExpectedValue for DiceExpr {
     fn expected(&self) -> f64 {
         match self {
@@ -88,6 +829,18 @@ impl ExpectedValue for DiceExpr {
             DiceExpr::Times(n, x) => (*n as f64) * x.expected(),
             DiceExpr::Plus(xa, xb) => xa.expected() + xb.expected(),
             DiceExpr::Const(v) => *v as f64,
+            DiceExpr::Fudge => 0.0,
+            DiceExpr::Faces(fs) => (fs.iter().sum::<Value>() as f64) / (fs.len() as f64),
+            DiceExpr::Percentile => 50.5,
+            DiceExpr::KeepHighest(..) | DiceExpr::KeepLowest(..) | DiceExpr::RerollBelow(..) |
+            DiceExpr::Minimum(..) |
+            DiceExpr::DivFloor(..) => self.distribution().expected(),
+            DiceExpr::CountSuccesses(t, x) => {
+                let (n, trial) = x.trials();
+                (n as f64) * trial.prob_pass(*t)
+            },
+            DiceExpr::Neg(x) => -x.expected(),
+            DiceExpr::Mul(x, k) => (*k as f64) * x.expected(),
         }
     }
 }
@@ -96,10 +849,32 @@ impl DiceRoll {
     /// Get the numerical value of a DiceRoll.
     pub fn value(&self) -> Value {
         match self {
-            DiceRoll::Die(_, v) => *v,
+            DiceRoll::Die(_, v) | DiceRoll::Fixed(_, v) => *v,
+            DiceRoll::RerolledLower(first, second) => first.value().min(second.value()),
             DiceRoll::Times(_, _, drs) => drs.iter().map(DiceRoll::value).sum(),
             DiceRoll::Plus(_, _, va, vb) => va.value() + vb.value(),
-            DiceRoll::Const(v) => *v,
+            DiceRoll::Const(v) | DiceRoll::Fudge(v) | DiceRoll::Faces(_, v) => *v,
+            DiceRoll::Percentile(0, 0) => 100,
+            DiceRoll::Percentile(tens, ones) => tens + ones,
+            DiceRoll::KeepHighest(_, k, _, drs) => {
+                let mut vs: Vec<Value> = drs.iter().map(DiceRoll::value).collect();
+                vs.sort_unstable_by(|a, b| b.cmp(a));
+                vs.iter().take(*k).sum()
+            },
+            DiceRoll::KeepLowest(_, k, _, drs) => {
+                let mut vs: Vec<Value> = drs.iter().map(DiceRoll::value).collect();
+                vs.sort_unstable();
+                vs.iter().take(*k).sum()
+            },
+            DiceRoll::RerollBelow(_, _, _, drs) => drs[drs.len() - 1].value(),
+            DiceRoll::Minimum(m, _, dr) => dr.value().max(*m),
+            DiceRoll::CountSuccesses(t, _, dr) => match &**dr {
+                DiceRoll::Times(_, _, drs) => drs.iter().filter(|dr| dr.value() >= *t).count() as Value,
+                dr => if dr.value() >= *t { 1 } else { 0 },
+            },
+            DiceRoll::Neg(_, dr) => -dr.value(),
+            DiceRoll::Mul(_, k, dr) => dr.value() * k,
+            DiceRoll::DivFloor(_, d, dr) => div_floor(dr.value(), *d),
         }
     }
 
@@ -107,9 +882,730 @@ impl DiceRoll {
     pub fn expr(&self) -> DiceExpr {
         match self {
             DiceRoll::Die(d, _) => DiceExpr::Die(*d),
-            DiceRoll::Times(n, x, _) => DiceExpr::Times(*n, Rc::clone(x)),
-            DiceRoll::Plus(xa, xb, _, _) => DiceExpr::Plus(Rc::clone(xa), Rc::clone(xb)),
+            DiceRoll::Fixed(x, _) => (**x).clone(),
+            DiceRoll::RerolledLower(first, _) => first.expr(),
+            DiceRoll::Times(n, x, _) => DiceExpr::Times(*n, Arc::clone(x)),
+            DiceRoll::Plus(xa, xb, _, _) => DiceExpr::Plus(Arc::clone(xa), Arc::clone(xb)),
             DiceRoll::Const(v) => DiceExpr::Const(*v),
+            DiceRoll::Fudge(_) => DiceExpr::Fudge,
+            DiceRoll::Faces(fs, _) => DiceExpr::Faces(fs.clone()),
+            DiceRoll::Percentile(..) => DiceExpr::Percentile,
+            DiceRoll::KeepHighest(n, k, x, _) => DiceExpr::KeepHighest(*n, *k, Arc::clone(x)),
+            DiceRoll::KeepLowest(n, k, x, _) => DiceExpr::KeepLowest(*n, *k, Arc::clone(x)),
+            DiceRoll::RerollBelow(t, once, x, _) => DiceExpr::RerollBelow(*t, *once, Arc::clone(x)),
+            DiceRoll::Minimum(m, x, _) => DiceExpr::Minimum(*m, Arc::clone(x)),
+            DiceRoll::CountSuccesses(t, x, _) => DiceExpr::CountSuccesses(*t, Arc::clone(x)),
+            DiceRoll::Neg(x, _) => DiceExpr::Neg(Arc::clone(x)),
+            DiceRoll::Mul(x, k, _) => DiceExpr::Mul(Arc::clone(x), *k),
+            DiceRoll::DivFloor(x, d, _) => DiceExpr::DivFloor(Arc::clone(x), *d),
+        }
+    }
+}
+
+impl DiceExpr {
+    /// Whether this is a single die, possibly with per-die modifiers, which is written without a
+    /// count (e.g. `d6` or `d6ro<2`).
+    fn is_atom(&self) -> bool {
+        match self {
+            DiceExpr::Die(_) | DiceExpr::Fudge | DiceExpr::Percentile | DiceExpr::Faces(_) => true,
+            DiceExpr::RerollBelow(_, _, x) | DiceExpr::Minimum(_, x) => x.is_atom(),
+            _ => false,
+        }
+    }
+
+    fn fmt_atom(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiceExpr::RerollBelow(t, once, x) => {
+                x.fmt_atom(f)?;
+                write!(f, "r{}<{}", if *once { "o" } else { "" }, t)
+            },
+            DiceExpr::Minimum(m, x) => {
+                x.fmt_atom(f)?;
+                write!(f, "min{}", m)
+            },
+            DiceExpr::Die(d) => write!(f, "d{}", d.0),
+            DiceExpr::Fudge => write!(f, "dF"),
+            DiceExpr::Faces(fs) => {
+                write!(f, "d{{")?;
+                for (idx, v) in fs.iter().enumerate() {
+                    write!(f, "{}{}", if idx == 0 { "" } else { "," }, v)?;
+                }
+                write!(f, "}}")
+            },
+            DiceExpr::Percentile => write!(f, "d%"),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Write n of this expression: `2d6` for dice, `2(1d6+1)` otherwise.
+    fn fmt_times(&self, f: &mut fmt::Formatter, n: usize) -> fmt::Result {
+        if self.is_atom() {
+            write!(f, "{}", n)?;
+            self.fmt_atom(f)
+        } else {
+            write!(f, "{}({})", n, self)
+        }
+    }
+
+    /// Whether this can be negated, multiplied or divided without parentheses: `-1d4` and
+    /// `1d4*2`, but `-(1d4+1)` and `(1d4+1)*2`.
+    fn is_operand(&self) -> bool {
+        match self {
+            DiceExpr::Plus(..) | DiceExpr::Neg(_) => false,
+            DiceExpr::Const(v) => *v >= 0,
+            _ => true,
+        }
+    }
+
+    /// Write this expression as the operand of a negation, multiplication or division,
+    /// parenthesized if needed.
+    fn fmt_operand(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_operand() {
+            write!(f, "{}", self)
+        } else {
+            write!(f, "({})", self)
+        }
+    }
+}
+
+/// Canonical dice notation, e.g. `"2d6+3"`.
+impl fmt::Display for DiceExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            x if x.is_atom() => x.fmt_times(f, 1),
+            DiceExpr::Times(n, x) => x.fmt_times(f, *n),
+            DiceExpr::Plus(xa, xb) => match **xb {
+                DiceExpr::Const(v) if v < 0 => write!(f, "{}-{}", xa, -v),
+                DiceExpr::Neg(ref x) => {
+                    write!(f, "{}-", xa)?;
+                    x.fmt_operand(f)
+                },
+                ref xb => {
+                    write!(f, "{}+", xa)?;
+                    xb.fmt_operand(f)
+                },
+            },
+            DiceExpr::Const(v) => write!(f, "{}", v),
+            DiceExpr::KeepHighest(n, k, x) => {
+                x.fmt_times(f, *n)?;
+                write!(f, "kh{}", k)
+            },
+            DiceExpr::KeepLowest(n, k, x) => {
+                x.fmt_times(f, *n)?;
+                write!(f, "kl{}", k)
+            },
+            DiceExpr::RerollBelow(t, once, x) => {
+                write!(f, "({})r{}<{}", x, if *once { "o" } else { "" }, t)
+            },
+            DiceExpr::Minimum(m, x) => write!(f, "({})min{}", x, m),
+            DiceExpr::CountSuccesses(t, x) => match **x {
+                DiceExpr::Times(..) | DiceExpr::Die(_) => write!(f, "{}>={}", x, t),
+                ref x => write!(f, "({})>={}", x, t),
+            },
+            DiceExpr::Neg(x) => {
+                write!(f, "-")?;
+                x.fmt_operand(f)
+            },
+            DiceExpr::Mul(x, k) => {
+                x.fmt_operand(f)?;
+                write!(f, "*{}", k)
+            },
+            DiceExpr::DivFloor(x, d) => {
+                x.fmt_operand(f)?;
+                write!(f, "/{}", d)
+            },
+            DiceExpr::Die(_) | DiceExpr::Fudge | DiceExpr::Percentile | DiceExpr::Faces(_) => unreachable!(),
+        }
+    }
+}
+
+impl DiceRoll {
+    /// Whether this roll could have come from rolling the expression: the structure matches, every
+    /// die result is in range, and counts, keeps, and rerolls are consistent. This is meant for
+    /// auditing stored rolls.
+    pub fn is_consistent_with(&self, ex: &DiceExpr) -> bool {
+        match (self, ex) {
+            (DiceRoll::Die(d, v), DiceExpr::Die(ed)) => d == ed && 1 <= *v && *v <= d.0,
+            (DiceRoll::Fixed(x, v), ex) =>
+                **x == *ex && ex.test_die().is_some() && ex.min() <= *v && *v <= ex.max(),
+            (DiceRoll::RerolledLower(first, second), ex) =>
+                ex.test_die().is_some() && first.is_consistent_with(ex) && second.is_consistent_with(ex),
+            (DiceRoll::Times(n, x, drs), DiceExpr::Times(en, ex)) =>
+                n == en && **x == **ex && drs.len() == *n && drs.iter().all(|dr| dr.is_consistent_with(ex)),
+            (DiceRoll::Plus(xa, xb, va, vb), DiceExpr::Plus(exa, exb)) =>
+                xa == exa && xb == exb && va.is_consistent_with(exa) && vb.is_consistent_with(exb),
+            (DiceRoll::Const(v), DiceExpr::Const(ev)) => v == ev,
+            (DiceRoll::Fudge(v), DiceExpr::Fudge) => -1 <= *v && *v <= 1,
+            (DiceRoll::Faces(fs, v), DiceExpr::Faces(efs)) => fs == efs && fs.contains(v),
+            (DiceRoll::Percentile(tens, ones), DiceExpr::Percentile) =>
+                (0..10).contains(&(tens / 10)) && tens % 10 == 0 && (0..10).contains(ones),
+            (DiceRoll::KeepHighest(n, k, x, drs), DiceExpr::KeepHighest(en, ek, ex)) |
+            (DiceRoll::KeepLowest(n, k, x, drs), DiceExpr::KeepLowest(en, ek, ex)) =>
+                n == en && k == ek && **x == **ex && drs.len() == *n
+                    && drs.iter().all(|dr| dr.is_consistent_with(ex)),
+            (DiceRoll::RerollBelow(t, once, x, drs), DiceExpr::RerollBelow(et, eonce, ex)) => {
+                let last = match drs.last() {
+                    Some(dr) => dr.value(),
+                    None => return false,
+                };
+                let stopped = if *once {
+                    drs.len() == 2 || (drs.len() == 1 && last > *t)
+                } else {
+                    last > *t || (drs.len() == 1 && ex.max() <= *t)
+                };
+                t == et && once == eonce && **x == **ex && stopped
+                    && drs[..drs.len() - 1].iter().all(|dr| dr.value() <= *t)
+                    && drs.iter().all(|dr| dr.is_consistent_with(ex))
+            },
+            (DiceRoll::Minimum(m, x, dr), DiceExpr::Minimum(em, ex)) =>
+                m == em && **x == **ex && dr.is_consistent_with(ex),
+            (DiceRoll::CountSuccesses(t, x, dr), DiceExpr::CountSuccesses(et, ex)) =>
+                t == et && **x == **ex && dr.is_consistent_with(ex),
+            (DiceRoll::Neg(x, dr), DiceExpr::Neg(ex)) => **x == **ex && dr.is_consistent_with(ex),
+            (DiceRoll::Mul(x, k, dr), DiceExpr::Mul(ex, ek)) |
+            (DiceRoll::DivFloor(x, k, dr), DiceExpr::DivFloor(ex, ek)) =>
+                k == ek && **x == **ex && dr.is_consistent_with(ex),
+            _ => false,
+        }
+    }
+
+    /// A compact rendering for logs: the expression, every die result in the order rolled, and
+    /// the total, as in `2d6+3:4,2=9`. Percentile dice contribute both dice.
+    pub fn compact(&self) -> Compact<'_> {
+        Compact(self)
+    }
+
+    fn fmt_leaves(&self, f: &mut fmt::Formatter, first: &mut bool) -> fmt::Result {
+        let mut leaf = |f: &mut fmt::Formatter, v: Value| {
+            let r = write!(f, "{}{}", if *first { "" } else { "," }, v);
+            *first = false;
+            r
+        };
+        match self {
+            DiceRoll::Die(_, v) | DiceRoll::Fixed(_, v) | DiceRoll::Fudge(v) | DiceRoll::Faces(_, v) =>
+                leaf(f, *v),
+            DiceRoll::Percentile(tens, ones) => {
+                leaf(f, *tens)?;
+                leaf(f, *ones)
+            },
+            DiceRoll::Const(_) => Ok(()),
+            DiceRoll::RerolledLower(a, b) => {
+                a.fmt_leaves(f, first)?;
+                b.fmt_leaves(f, first)
+            },
+            DiceRoll::Times(_, _, drs) | DiceRoll::KeepHighest(_, _, _, drs) |
+            DiceRoll::KeepLowest(_, _, _, drs) | DiceRoll::RerollBelow(_, _, _, drs) => {
+                for dr in drs {
+                    dr.fmt_leaves(f, first)?;
+                }
+                Ok(())
+            },
+            DiceRoll::Plus(_, _, va, vb) => {
+                va.fmt_leaves(f, first)?;
+                vb.fmt_leaves(f, first)
+            },
+            DiceRoll::CountSuccesses(_, _, dr) | DiceRoll::Minimum(_, _, dr) | DiceRoll::Neg(_, dr) |
+            DiceRoll::Mul(_, _, dr) |
+            DiceRoll::DivFloor(_, _, dr) => dr.fmt_leaves(f, first),
+        }
+    }
+
+    /// Write the roll with its intermediate values, but without the total.
+    fn fmt_parts(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            dr if dr.expr().is_atom() => fmt_rolls(f, &dr.expr(), core::slice::from_ref(dr)),
+            DiceRoll::Times(_, x, drs) => fmt_rolls(f, x, drs),
+            DiceRoll::Fixed(x, v) => write!(f, "{} [{} (fixed)]", x, v),
+            DiceRoll::RerolledLower(a, b) => {
+                a.fmt_parts(f)?;
+                write!(f, " -> ")?;
+                b.fmt_parts(f)?;
+                write!(f, " (lower kept)")
+            },
+            DiceRoll::KeepHighest(_, k, x, drs) => {
+                fmt_rolls(f, x, drs)?;
+                write!(f, " kh{}", k)
+            },
+            DiceRoll::KeepLowest(_, k, x, drs) => {
+                fmt_rolls(f, x, drs)?;
+                write!(f, " kl{}", k)
+            },
+            DiceRoll::Plus(_, _, va, vb) => {
+                va.fmt_parts(f)?;
+                match **vb {
+                    DiceRoll::Const(v) if v < 0 => write!(f, " - {}", -v),
+                    DiceRoll::Neg(_, ref dr) => {
+                        write!(f, " - ")?;
+                        dr.fmt_operand_parts(f)
+                    },
+                    ref vb => {
+                        write!(f, " + ")?;
+                        vb.fmt_parts(f)
+                    },
+                }
+            },
+            DiceRoll::Const(v) => write!(f, "{}", v),
+            DiceRoll::RerollBelow(t, once, _, drs) => {
+                write!(f, "(")?;
+                for (idx, dr) in drs.iter().enumerate() {
+                    write!(f, "{}", if idx == 0 { "" } else { " -> " })?;
+                    dr.fmt_parts(f)?;
+                }
+                write!(f, ")r{}<{}", if *once { "o" } else { "" }, t)
+            },
+            DiceRoll::Minimum(m, _, dr) => {
+                write!(f, "(")?;
+                dr.fmt_parts(f)?;
+                write!(f, ")min{}", m)
+            },
+            DiceRoll::CountSuccesses(t, _, dr) => {
+                dr.fmt_parts(f)?;
+                write!(f, " >={}", t)
+            },
+            DiceRoll::Neg(_, dr) => {
+                write!(f, "-")?;
+                dr.fmt_operand_parts(f)
+            },
+            DiceRoll::Mul(_, k, dr) => {
+                dr.fmt_operand_parts(f)?;
+                write!(f, " * {}", k)
+            },
+            DiceRoll::DivFloor(_, d, dr) => {
+                dr.fmt_operand_parts(f)?;
+                write!(f, " / {}", d)
+            },
+            DiceRoll::Die(..) | DiceRoll::Fudge(_) | DiceRoll::Percentile(..) |
+            DiceRoll::Faces(..) => unreachable!(),
+        }
+    }
+
+    /// Write the parts of a roll as an operand, parenthesized as in `DiceExpr::fmt_operand`.
+    fn fmt_operand_parts(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.expr().is_operand() {
+            self.fmt_parts(f)
+        } else {
+            write!(f, "(")?;
+            self.fmt_parts(f)?;
+            write!(f, ")")
         }
     }
+
+    /// Write the result of a single die, with any rerolls or minimum: `4`, `1 -> 5`, or
+    /// `3 (min 10)`. Fixed results are marked, as in `17 (fixed)`. Percentile dice show both dice, as in `00+7`.
+    fn fmt_atom_roll(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiceRoll::Percentile(tens, ones) => write!(f, "{:02}+{}", tens, ones),
+            DiceRoll::Fixed(_, v) => write!(f, "{} (fixed)", v),
+            DiceRoll::RerolledLower(a, b) => {
+                a.fmt_atom_roll(f)?;
+                write!(f, " -> ")?;
+                b.fmt_atom_roll(f)?;
+                write!(f, " (lower kept)")
+            },
+            DiceRoll::RerollBelow(_, _, _, drs) => {
+                for (idx, dr) in drs.iter().enumerate() {
+                    write!(f, "{}", if idx == 0 { "" } else { " -> " })?;
+                    dr.fmt_atom_roll(f)?;
+                }
+                Ok(())
+            },
+            DiceRoll::Minimum(m, _, dr) => {
+                dr.fmt_atom_roll(f)?;
+                if dr.value() < *m {
+                    write!(f, " (min {})", m)?;
+                }
+                Ok(())
+            },
+            dr => write!(f, "{}", dr.value()),
+        }
+    }
+}
+
+/// Write rolls of the same expression: `2d6 [4, 2]` for dice, `2(1d6 [3] + 1, ...)` otherwise.
+fn fmt_rolls(f: &mut fmt::Formatter, x: &DiceExpr, drs: &[DiceRoll]) -> fmt::Result {
+    if x.is_atom() {
+        x.fmt_times(f, drs.len())?;
+        write!(f, " [")?;
+        for (idx, dr) in drs.iter().enumerate() {
+            write!(f, "{}", if idx == 0 { "" } else { ", " })?;
+            dr.fmt_atom_roll(f)?;
+        }
+        write!(f, "]")
+    } else {
+        write!(f, "{}(", drs.len())?;
+        for (idx, dr) in drs.iter().enumerate() {
+            write!(f, "{}", if idx == 0 { "" } else { ", " })?;
+            dr.fmt_parts(f)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// See `DiceRoll::compact`.
+pub struct Compact<'a>(&'a DiceRoll);
+
+impl<'a> fmt::Display for Compact<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:", self.0.expr())?;
+        self.0.fmt_leaves(f, &mut true)?;
+        write!(f, "={}", self.0.value())
+    }
+}
+
+/// The roll with its intermediate values and total, e.g. `"2d6 [4, 2] + 3 = 9"`.
+impl fmt::Display for DiceRoll {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_parts(f)?;
+        write!(f, " = {}", self.value())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let ex = DiceExpr::parse("2d6+3").unwrap();
+        assert_eq!(ex.to_string(), "2d6+3");
+        let (xa, xb) = match &ex {
+            DiceExpr::Plus(xa, xb) => (Arc::clone(xa), Arc::clone(xb)),
+            _ => unreachable!(),
+        };
+        let roll = DiceRoll::Plus(xa, xb,
+            Arc::new(DiceRoll::Times(2, Arc::new(DiceExpr::Die(Die::d6())), vec![
+                DiceRoll::Die(Die::d6(), 4), DiceRoll::Die(Die::d6(), 2),
+            ])),
+            Arc::new(DiceRoll::Const(3)),
+        );
+        assert_eq!(roll.to_string(), "2d6 [4, 2] + 3 = 9");
+    }
+
+    #[test]
+    fn round_trip() {
+        let d6 = Arc::new(DiceExpr::Die(Die::d6()));
+        let sum = Arc::new(DiceExpr::parse("1d6+1").unwrap());
+        let exprs = [
+            DiceExpr::Times(2, Arc::clone(&sum)),
+            DiceExpr::KeepHighest(2, 1, Arc::clone(&sum)),
+            DiceExpr::RerollBelow(2, false, Arc::new(DiceExpr::Times(2, Arc::clone(&d6)))),
+            DiceExpr::RerollBelow(2, true, Arc::new(DiceExpr::Minimum(3, Arc::clone(&d6)))),
+            DiceExpr::Minimum(10, Arc::clone(&sum)),
+            DiceExpr::CountSuccesses(5, Arc::new(DiceExpr::parse("4d6kh3").unwrap())),
+            DiceExpr::Plus(Arc::new(DiceExpr::Const(1)), Arc::clone(&sum)),
+            DiceExpr::Mul(Arc::clone(&sum), 2),
+            DiceExpr::Mul(Arc::new(DiceExpr::Const(-3)), 2),
+            DiceExpr::parse("-(1d6+2)/2").unwrap(),
+        ];
+        for ex in exprs.iter() {
+            assert_eq!(DiceExpr::parse(&ex.to_string()).as_ref(), Ok(ex), "{}", ex);
+        }
+        assert_eq!(DiceExpr::parse("(1d6)"), Ok(DiceExpr::Die(Die::d6())));
+        assert_eq!(DiceExpr::parse("2(1d6+1)").unwrap().to_string(), "2(1d6+1)");
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn keep() {
+        let adv = DiceExpr::parse("2d20kh1").unwrap();
+        assert!(close(adv.expected(), 13.825));
+        assert!(close(adv.prob_pass(20), 1.0 - 0.95 * 0.95));
+        let dis = DiceExpr::parse("2d20kl1").unwrap();
+        assert!(close(dis.expected(), 7.175));
+        let stats = DiceExpr::parse("4d6dl1").unwrap();
+        assert_eq!(stats.to_string(), "4d6kh3");
+        assert!(close(stats.expected(), 15869.0 / 1296.0));
+        assert!(close(stats.cum_prob(3), 1.0 / 1296.0));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn reroll() {
+        let gwf = DiceExpr::parse("2d6ro<2").unwrap();
+        assert_eq!(gwf.to_string(), "2d6ro<2");
+        assert!(close(gwf.expected(), 2.0 * 25.0 / 6.0));
+        let above = DiceExpr::parse("d6r<2").unwrap();
+        assert!(close(above.expected(), 4.5));
+        let roll = gwf.roll(&mut rand::thread_rng());
+        assert!(roll.value() >= 2 && roll.value() <= 12);
+        assert!(roll.to_string().starts_with("2d6ro<2 ["));
+    }
+
+    #[test]
+    fn quantile() {
+        let ex = DiceExpr::parse("2d6+3").unwrap();
+        assert_eq!(ex.quantile(0.0), 5);
+        assert_eq!(ex.quantile(0.5), 10);
+        assert_eq!(ex.quantile(0.9), 13);
+        assert_eq!(ex.quantile(1.0), 15);
+    }
+
+    #[test]
+    fn bounds() {
+        let ex = DiceExpr::parse("4d6kh3 + 2d8 + 1").unwrap();
+        assert_eq!((ex.min(), ex.max()), (6, 35));
+        let ex = DiceExpr::parse("d6r<2").unwrap();
+        assert_eq!((ex.min(), ex.max()), (3, 6));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn successes() {
+        let pool = DiceExpr::parse("5d10>=8").unwrap();
+        assert_eq!(pool.to_string(), "5d10>=8");
+        assert!(close(pool.expected(), 1.5));
+        assert!(close(pool.distribution().expected(), 1.5));
+        assert!(close(pool.cum_prob(0), 0.7 * 0.7 * 0.7 * 0.7 * 0.7));
+        assert_eq!((pool.min(), pool.max()), (0, 5));
+        let roll = pool.roll(&mut rand::thread_rng());
+        assert!(roll.value() >= 0 && roll.value() <= 5);
+    }
+
+    #[test]
+    fn fudge() {
+        let ex = DiceExpr::parse("4dF+1").unwrap();
+        assert_eq!(ex.to_string(), "4dF+1");
+        assert!(close(ex.expected(), 1.0));
+        assert_eq!((ex.min(), ex.max()), (-3, 5));
+        assert!(close(ex.cum_prob(-3), 1.0 / 81.0));
+    }
+
+    #[test]
+    fn percentile() {
+        let ex = DiceExpr::parse("d%").unwrap();
+        assert_eq!(ex.to_string(), "1d%");
+        assert!(close(ex.expected(), ex.distribution().expected()));
+        assert_eq!(DiceRoll::Percentile(0, 0).value(), 100);
+        assert_eq!(DiceRoll::Percentile(0, 7).to_string(), "1d% [00+7] = 7");
+        assert_eq!(DiceRoll::Percentile(90, 0).value(), 90);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn audit() {
+        let ex = DiceExpr::parse("2d6+3").unwrap();
+        let roll = DiceRoll::Plus(
+            Arc::new(DiceExpr::parse("2d6").unwrap()), Arc::new(DiceExpr::Const(3)),
+            Arc::new(DiceRoll::Times(2, Arc::new(DiceExpr::Die(Die::d6())), vec![
+                DiceRoll::Die(Die::d6(), 4), DiceRoll::Die(Die::d6(), 2),
+            ])),
+            Arc::new(DiceRoll::Const(3)),
+        );
+        assert_eq!(roll.compact().to_string(), "2d6+3:4,2=9");
+        assert!(roll.is_consistent_with(&ex));
+        assert!(!roll.is_consistent_with(&DiceExpr::parse("2d6+4").unwrap()));
+        let forged = DiceRoll::Times(2, Arc::new(DiceExpr::Die(Die::d6())), vec![
+            DiceRoll::Die(Die::d6(), 7), DiceRoll::Die(Die::d6(), 2),
+        ]);
+        assert!(!forged.is_consistent_with(&DiceExpr::parse("2d6").unwrap()));
+        let rng = &mut rand::thread_rng();
+        for s in &["2d6ro<2+1", "4d6kh3", "5d10>=8", "d%", "4dF"] {
+            let ex = DiceExpr::parse(s).unwrap();
+            assert!(ex.roll(rng).is_consistent_with(&ex));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn negation() {
+        let ex = DiceExpr::parse("2d8 - 1d4").unwrap();
+        assert_eq!(ex.to_string(), "2d8-1d4");
+        assert!(close(ex.expected(), 6.5));
+        assert_eq!((ex.min(), ex.max()), (-2, 15));
+        assert!(close(ex.cum_prob(-2), 1.0 / 256.0));
+        let ex = DiceExpr::parse("1d20 - 2").unwrap();
+        assert_eq!(ex, DiceExpr::Plus(Arc::new(DiceExpr::Die(Die::d20())), Arc::new(DiceExpr::Const(-2))));
+        let ex = DiceExpr::parse("-1d4+1").unwrap();
+        assert_eq!(ex.to_string(), "-1d4+1");
+        assert_eq!((ex.min(), ex.max()), (-3, 0));
+        let rng = &mut rand::thread_rng();
+        let roll = DiceExpr::parse("1d6-1d4").unwrap().roll(rng);
+        assert!(roll.to_string().contains(" - 1d4 ["));
+        assert!(roll.is_consistent_with(&roll.expr()));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn scaling() {
+        let ex = DiceExpr::parse("10d6*10").unwrap();
+        assert_eq!(ex.to_string(), "10d6*10");
+        assert!(close(ex.expected(), 350.0));
+        assert_eq!((ex.min(), ex.max()), (100, 600));
+        let half = DiceExpr::parse("3d6/2 + 1").unwrap();
+        assert_eq!(half.to_string(), "3d6/2+1");
+        assert_eq!((half.min(), half.max()), (2, 10));
+        assert!(close(half.expected(), half.distribution().expected()));
+        assert!(close(DiceExpr::parse("1d2/2").unwrap().expected(), 0.5));
+        assert_eq!(div_floor(-3, 2), -2);
+        assert_eq!(DiceExpr::parse("1d6/0").unwrap_err().kind, parse::ParseErrorKind::ZeroDivisor);
+        let rng = &mut rand::thread_rng();
+        let roll = half.roll(rng);
+        assert!(roll.to_string().starts_with("3d6 ["));
+        assert!(roll.is_consistent_with(&half));
+    }
+
+    #[test]
+    fn simplify() {
+        let ex = DiceExpr::parse("1d6 + 2 + 1d8 + 1d6 + 3").unwrap().simplify();
+        assert_eq!(ex.to_string(), "1d8+2d6+5");
+        assert_eq!(
+            DiceExpr::parse("1d6+1d6").unwrap().simplify(),
+            DiceExpr::parse("2d6").unwrap().simplify(),
+        );
+        assert_eq!(DiceExpr::parse("1d4 - 1 + 1").unwrap().simplify().to_string(), "1d4");
+        assert_eq!(DiceExpr::parse("2*3 + 1").unwrap().simplify(), DiceExpr::Const(7));
+        assert_eq!(DiceExpr::parse("2d6ro<2 + 1d6ro<2").unwrap().simplify().to_string(), "3d6ro<2");
+        let simple = |s: &str| DiceExpr::parse(s).unwrap().simplify();
+        assert_eq!(simple("dF+d%"), simple("d%+dF"));
+        assert_eq!(simple("2d20kh1+1d4+d%"), simple("d%+1d4+2d20kh1"));
+        assert_eq!(simple("1d6-1d6"), DiceExpr::Const(0));
+        assert_eq!(simple("1d4+1d6-1d4+2").to_string(), "1d6+2");
+        assert_eq!(simple("1d8-2d6+1d6").to_string(), "1d8-1d6");
+        assert_eq!(simple("-1d4+1d8"), simple("1d8-1d4"));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn fast_rolls() {
+        let rng = &mut rand::thread_rng();
+        for s in &["4d6kh3+1", "2d6ro<2", "d6r<2", "5d10>=8", "d%", "4dF", "2d8-1d4", "3d6/2"] {
+            let ex = DiceExpr::parse(s).unwrap();
+            assert!(ex.values(rng).take(100).all(|v| ex.min() <= v && v <= ex.max()));
+        }
+        let hist = histogram(&DiceExpr::parse("2d6").unwrap(), 1000, rng);
+        assert_eq!(hist.values().sum::<usize>(), 1000);
+        assert!(hist.keys().all(|v| (2..=12).contains(v)));
+    }
+
+    #[test]
+    fn statblock() {
+        assert_eq!(DiceExpr::parse("2d6").unwrap().expected_statblock(), 7);
+        assert_eq!(DiceExpr::parse("1d8+2").unwrap().expected_statblock(), 6);
+        assert_eq!(DiceExpr::parse("1d4-3").unwrap().expected_statblock(), -1);
+    }
+
+    #[test]
+    fn approximating() {
+        assert_eq!(DiceExpr::approximating(7.0, Die::d6()).to_string(), "2d6");
+        assert_eq!(DiceExpr::approximating(16.0, Die::d8()).to_string(), "3d8+3");
+        assert_eq!(DiceExpr::approximating(2.0, Die::d8()).to_string(), "1d4");
+        assert_eq!(DiceExpr::approximating(3.0, Die::d8()).to_string(), "1d6");
+        assert_eq!(DiceExpr::approximating(0.5, Die::d6()).to_string(), "0");
+        for avg in 0..60 {
+            let ex = DiceExpr::approximating(avg as f64, Die::d10());
+            assert_eq!(ex.expected_statblock(), avg);
+            assert!(ex.min() >= 0, "{}", ex);
+        }
+    }
+
+    #[test]
+    fn crit() {
+        assert_eq!(DiceExpr::parse("2d6+1d8+3").unwrap().crit().to_string(), "4d6+2d8+3");
+        assert_eq!(DiceExpr::parse("1d12+4").unwrap().crit_with(1).simplify().to_string(), "3d12+4");
+        assert_eq!(DiceExpr::parse("2d6ro<2").unwrap().crit().to_string(), "4d6ro<2");
+        assert_eq!(DiceExpr::parse("5").unwrap().crit_with(1).to_string(), "5");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn minimum() {
+        let reliable = DiceExpr::parse("d20min10").unwrap();
+        assert_eq!(reliable.to_string(), "1d20min10");
+        assert!(close(reliable.expected(), (10.0 * 10.0 + (11..=20).sum::<isize>() as f64) / 20.0));
+        assert_eq!((reliable.min(), reliable.max()), (10, 20));
+        assert!(close(reliable.cum_prob(10), 0.5));
+        let roll = DiceRoll::Minimum(10, Arc::new(DiceExpr::Die(Die::d20())), Arc::new(DiceRoll::Die(Die::d20(), 3)));
+        assert_eq!(roll.to_string(), "1d20min10 [3 (min 10)] = 10");
+        let ex = DiceExpr::parse("2d6ro<2min3kh1").unwrap();
+        assert_eq!(ex.to_string(), "2d6ro<2min3kh1");
+        assert!(ex.roll(&mut rand::thread_rng()).is_consistent_with(&ex));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn faces() {
+        let loot = DiceExpr::parse("d{0,0,0,1,1,2}").unwrap();
+        assert_eq!(loot.to_string(), "1d{0,0,0,1,1,2}");
+        assert!(close(loot.expected(), 4.0 / 6.0));
+        assert!(close(loot.cum_prob(0), 0.5));
+        assert_eq!((loot.min(), loot.max()), (0, 2));
+        let ex = DiceExpr::parse("2d{-1,1}+1").unwrap();
+        assert_eq!((ex.min(), ex.max()), (-1, 3));
+        let roll = ex.roll(&mut rand::thread_rng());
+        assert!(roll.is_consistent_with(&ex));
+        assert!(DiceExpr::parse("d{}").is_err());
+        assert_eq!(Faces::new(Vec::new()), Err(NoFaces));
+        assert_eq!(Distribution(Vec::new()).quantile(0.5), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn equality() {
+        let (a, b) = (DiceExpr::parse("2d6+3").unwrap(), DiceExpr::parse("2d6 + 3").unwrap());
+        assert_eq!(a, b);
+        assert_ne!(a, DiceExpr::parse("1d6+1d6+3").unwrap());
+        let mut cache = std::collections::HashMap::new();
+        cache.insert(a.clone(), a.distribution());
+        assert!(cache.contains_key(&b));
+    }
+
+    #[test]
+    fn standard_dice() {
+        assert_eq!(Die::new(6), Ok(Die::d6()));
+        assert_eq!(Die::new(0), Err(InvalidDie(0)));
+        assert_eq!(StandardDie::try_from(Die::d12()), Ok(StandardDie::D12));
+        assert_eq!(StandardDie::try_from(Die::new(7).unwrap()), Err(Die::new(7).unwrap()));
+        assert!(StandardDie::ALL.iter().all(|sd| Die::from(*sd).0 == sd.sides()));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn fixed() {
+        let mut fixed = FixedResults::default();
+        fixed.push(Die::d20(), 17);
+        fixed.push(Die::d6(), 4);
+        let ex = DiceExpr::parse("2d20kh1+1d6").unwrap();
+        let roll = ex.roll_fixing(&mut rand::thread_rng(), &mut fixed);
+        assert!(fixed.0.is_empty());
+        assert_eq!(roll.to_string(), "2d20kh1 [17 (fixed)] + 1d6 [4 (fixed)] = 21");
+        assert!(roll.is_consistent_with(&ex));
+        // A fixed 1 stands; it isn't a natural 1 for Halfling Luck to reroll.
+        fixed.push(Die::d20(), 1);
+        let roll = DiceExpr::lucky_d20().roll_fixing(&mut rand::thread_rng(), &mut fixed);
+        assert_eq!(roll.value(), 1);
+        assert!(roll.is_consistent_with(&DiceExpr::lucky_d20()));
+        fixed.push_reroll_lower(Die::d20());
+        let ex = DiceExpr::parse("2d20kh1").unwrap();
+        let roll = ex.roll_fixing(&mut rand::thread_rng(), &mut fixed);
+        // The whole test is rerolled, advantage and all, rather than one of its d20s.
+        match &roll {
+            DiceRoll::RerolledLower(a, b) => {
+                assert!(matches!(&**b, DiceRoll::KeepHighest(2, 1, _, drs) if drs.len() == 2));
+                assert_eq!(roll.value(), a.value().min(b.value()));
+            },
+            _ => panic!("expected a reroll, not {:?}", roll),
+        }
+        assert!(roll.is_consistent_with(&ex));
+        fixed.push_reroll_lower(Die::d20());
+        let roll = DiceExpr::parse("1d20+2").unwrap().roll_fixing(&mut rand::thread_rng(), &mut fixed);
+        let vs = match &roll {
+            DiceRoll::Plus(_, _, va, _) => match &**va {
+                DiceRoll::RerolledLower(a, b) => (a.value(), b.value()),
+                _ => panic!("expected a reroll, not {:?}", va),
+            },
+            _ => unreachable!(),
+        };
+        assert_eq!(roll.to_string(), format!("1d20 [{} -> {} (lower kept)] + 2 = {}", vs.0, vs.1, vs.0.min(vs.1) + 2));
+    }
+
+    #[test]
+    fn lucky() {
+        let d20 = DiceExpr::lucky_d20();
+        assert!(close(d20.cum_prob(1), 1.0 / 400.0));
+        // A DC 11 save: 10/20 plus a 1/20 chance of rerolling into 10/20.
+        assert!(close(d20.prob_pass(11), 0.5 + 0.05 * 0.5));
+    }
 }
@@ -0,0 +1,51 @@
+use crate::citation::Citation;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A structured derivation of a computed value ("show your work"): the value, what it represents,
+/// the rule it follows (if any), and the values it was derived from.
+#[derive(Debug,Clone)]
+pub struct Derivation {
+    pub label: String,
+    pub value: f64,
+    pub citation: Option<Citation>,
+    pub children: Vec<Derivation>,
+}
+
+impl Derivation {
+    pub fn new<S: Into<String>>(label: S, value: f64) -> Derivation {
+        Derivation { label: label.into(), value, citation: None, children: Vec::new() }
+    }
+
+    /// Attach a rule citation.
+    pub fn cite(mut self, citation: Citation) -> Derivation {
+        self.citation = Some(citation);
+        self
+    }
+
+    /// Attach an input to this step.
+    pub fn with(mut self, child: Derivation) -> Derivation {
+        self.children.push(child);
+        self
+    }
+
+    /// Render the tree as indented text, one step per line.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        self.render_into(&mut out, 0);
+        out
+    }
+
+    fn render_into(&self, out: &mut String, depth: usize) {
+        out.push_str(&"  ".repeat(depth));
+        out.push_str(&format!("{}: {}", self.label, self.value));
+        if let Some(c) = self.citation {
+            out.push_str(&format!(" ({})", c));
+        }
+        out.push('\n');
+        for child in &self.children {
+            child.render_into(out, depth + 1);
+        }
+    }
+}
@@ -1,16 +1,29 @@
-extern crate rand;
+#![cfg_attr(not(feature = "std"), no_std)]
+
 #[macro_use]
+extern crate alloc;
+extern crate rand;
 extern crate nom;
 
 pub mod types;
 pub use types::*;
 pub mod util;
+pub mod citation;
+pub mod explain;
 pub mod dice;
+pub mod parse;
+pub mod check;
 pub mod space;
 pub mod damage;
 pub mod basetraits;
+pub mod armor;
+pub mod modifier;
+pub mod meta;
 pub mod action;
+pub mod spells;
+#[cfg(feature = "std")]
 pub mod creature;
+#[cfg(feature = "std")]
 pub mod combat;
 
 #[cfg(test)]
@@ -19,4 +32,14 @@ mod tests {
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    fn send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn thread_safe() {
+        send_sync::<crate::dice::DiceExpr>();
+        send_sync::<crate::dice::DiceRoll>();
+        #[cfg(feature = "std")]
+        send_sync::<crate::creature::Creature>();
+    }
 }
@@ -0,0 +1,45 @@
+//! Free-form metadata which integrating tools can attach to creatures (e.g. owner, source book,
+//! or homebrew flags) without changing this crate's types.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+
+/// A metadata value.
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MetaValue {
+    Str(String),
+    Num(f64),
+    /// A flag which is set by being present.
+    Tag,
+}
+
+/// A map of metadata keys to values, ordered by key so that serialization is stable.
+#[derive(Debug,Clone,Default,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metadata(pub BTreeMap<String, MetaValue>);
+
+impl Metadata {
+    pub fn get(&self, key: &str) -> Option<&MetaValue> {
+        self.0.get(key)
+    }
+
+    /// Set a value, returning the previous one, if any.
+    pub fn insert<S: Into<String>>(&mut self, key: S, value: MetaValue) -> Option<MetaValue> {
+        self.0.insert(key.into(), value)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<MetaValue> {
+        self.0.remove(key)
+    }
+
+    /// Set a flag.
+    pub fn tag<S: Into<String>>(&mut self, key: S) {
+        self.0.insert(key.into(), MetaValue::Tag);
+    }
+
+    /// Whether the key is present, with any value.
+    pub fn has(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+}
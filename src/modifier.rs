@@ -0,0 +1,114 @@
+use crate::basetraits::*;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::min;
+
+/// Where a modifier came from; retained so that a final number can be explained.
+#[derive(Debug,Clone,PartialEq,Eq,Hash)]
+pub enum ModifierSource {
+    Ability(Ability),
+    Proficiency,
+    /// An unattributed flat bonus, as given in a stat block (e.g. `Attack::to_hit_bonus`).
+    Bonus,
+    Item(String),
+    /// A spell or other game effect (e.g. "Bless").
+    Effect(String),
+    Cover,
+}
+
+/// How a modifier combines with others in the same stack.
+#[derive(Debug,Clone,PartialEq,Eq,Hash)]
+pub enum Stacking {
+    /// Always added.
+    Stacks,
+    /// Modifiers with the same source don't stack; only the most potent applies (5e PHB, p. 205).
+    SameSourceDoesNotStack,
+    /// Only the largest modifier within the named group applies, e.g. cover (5e PHB, p. 196).
+    BestInGroup(String),
+}
+
+/// A single bonus or penalty, with its provenance.
+#[derive(Debug,Clone)]
+pub struct Modifier {
+    pub source: ModifierSource,
+    pub value: isize,
+    pub stacking: Stacking,
+    /// An upper bound on the value contributed (e.g. the Dex cap of medium armor).
+    pub cap: Option<isize>,
+}
+
+impl Modifier {
+    /// A modifier which always stacks and has no cap.
+    pub fn new(source: ModifierSource, value: isize) -> Modifier {
+        Modifier { source, value, stacking: Stacking::Stacks, cap: None }
+    }
+
+    /// The value contributed after applying the cap.
+    pub fn effective(&self) -> isize {
+        match self.cap {
+            Some(c) => min(self.value, c),
+            None => self.value,
+        }
+    }
+}
+
+/// A collection of modifiers to a single roll (attack, check, or save).
+#[derive(Debug,Clone,Default)]
+pub struct ModifierStack(pub Vec<Modifier>);
+
+impl ModifierStack {
+    pub fn push(&mut self, m: Modifier) {
+        self.0.push(m);
+    }
+
+    /// Whether each modifier counts toward the total after stacking rules, in order. Ties are
+    /// resolved in favor of the earliest modifier.
+    pub fn applied(&self) -> Vec<(&Modifier, bool)> {
+        self.0.iter().enumerate().map(|(idx, m)| {
+            let beaten = |(oidx, o): (usize, &Modifier)| {
+                o.effective() > m.effective() || (o.effective() == m.effective() && oidx < idx)
+            };
+            let counts = match &m.stacking {
+                Stacking::Stacks => true,
+                Stacking::SameSourceDoesNotStack => !self.0.iter().enumerate()
+                    .filter(|(_, o)| o.stacking == m.stacking && o.source == m.source)
+                    .any(beaten),
+                Stacking::BestInGroup(_) => !self.0.iter().enumerate()
+                    .filter(|(_, o)| o.stacking == m.stacking)
+                    .any(beaten),
+            };
+            (m, counts)
+        }).collect()
+    }
+
+    /// The final modifier after stacking rules and caps.
+    pub fn total(&self) -> isize {
+        self.applied().iter().filter(|(_, c)| *c).map(|(m, _)| m.effective()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stacking_rules() {
+        let mut stack = ModifierStack::default();
+        stack.push(Modifier::new(ModifierSource::Ability(Ability::Dex), 4));
+        stack.push(Modifier { cap: Some(2), ..Modifier::new(ModifierSource::Item("Half plate".into()), 3) });
+        let bless = Modifier {
+            stacking: Stacking::SameSourceDoesNotStack,
+            ..Modifier::new(ModifierSource::Effect("Bless".into()), 2)
+        };
+        stack.push(bless.clone());
+        stack.push(bless);
+        let cover = |v| Modifier {
+            stacking: Stacking::BestInGroup("cover".into()),
+            ..Modifier::new(ModifierSource::Cover, v)
+        };
+        stack.push(cover(2));
+        stack.push(cover(5));
+        assert_eq!(stack.total(), 4 + 2 + 2 + 5);
+    }
+}
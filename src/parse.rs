@@ -0,0 +1,521 @@
+//! Parsers for standard notation, built on nom's core (allocation-free) combinators.
+
+use crate::dice::*;
+use crate::action::*;
+use crate::basetraits::Ability;
+use crate::damage::DamageKind;
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+
+use nom::IResult;
+use nom::error::ErrorKind;
+use nom::branch::alt;
+use nom::bytes::complete::{tag, tag_no_case};
+use nom::character::complete::{alpha1, char, digit1, multispace0, one_of, space0};
+use nom::combinator::{all_consuming, map, map_opt, map_res, opt, verify};
+use nom::multi::fold_many0;
+use nom::sequence::{delimited, pair, preceded};
+
+/// What went wrong while parsing.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ParseErrorKind {
+    /// The input doesn't follow the notation.
+    Syntax,
+    /// A number is too large to represent.
+    Overflow,
+    /// A die has no sides (e.g. `"1d0"`).
+    InvalidDie,
+    /// An expression is divided by zero (e.g. `"1d6/0"`).
+    ZeroDivisor,
+}
+
+/// An error from parsing notation, with the byte offset at which it occurred.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct ParseError {
+    pub offset: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.kind {
+            ParseErrorKind::Syntax => "malformed dice notation",
+            ParseErrorKind::Overflow => "number too large",
+            ParseErrorKind::InvalidDie => "die must have at least one side",
+            ParseErrorKind::ZeroDivisor => "division by zero",
+        };
+        write!(f, "{} at offset {}", what, self.offset)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+/// Run a parser over the entire input, converting nom's errors into a `ParseError`.
+pub fn complete<'a, O, F>(input: &'a str, parser: F) -> Result<O, ParseError>
+    where F: Fn(&'a str) -> IResult<&'a str, O>
+{
+    match all_consuming(parser)(input) {
+        Ok((_, o)) => Ok(o),
+        Err(nom::Err::Error((rest, k))) | Err(nom::Err::Failure((rest, k))) => Err(ParseError {
+            offset: input.len() - rest.len(),
+            kind: match k {
+                ErrorKind::MapRes => ParseErrorKind::Overflow,
+                INVALID_DIE => ParseErrorKind::InvalidDie,
+                ZERO_DIVISOR => ParseErrorKind::ZeroDivisor,
+                _ => ParseErrorKind::Syntax,
+            },
+        }),
+        Err(nom::Err::Incomplete(_)) => Err(ParseError {
+            offset: input.len(),
+            kind: ParseErrorKind::Syntax,
+        }),
+    }
+}
+
+/// The nom error kind reported for a zero divisor, which no core combinator produces.
+const ZERO_DIVISOR: ErrorKind = ErrorKind::NonEmpty;
+
+/// The nom error kind reported for a die without sides, which no core combinator produces.
+const INVALID_DIE: ErrorKind = ErrorKind::Fix;
+
+fn ws<'a, O, F>(f: F) -> impl Fn(&'a str) -> IResult<&'a str, O>
+    where F: Fn(&'a str) -> IResult<&'a str, O>
+{
+    delimited(space0, f, space0)
+}
+
+fn value(i: &str) -> IResult<&str, Value> {
+    map_res(digit1, |s: &str| s.parse::<Value>())(i)
+}
+
+fn count(i: &str) -> IResult<&str, usize> {
+    map_res(digit1, |s: &str| s.parse::<usize>())(i)
+}
+
+/// `NdX` or `dX` (where X may be `F` for Fudge dice, `%` for percentile dice, or `{a,b,...}` for
+/// custom faces), optionally followed by reroll suffixes (`r<T` to reroll results at or below
+/// T until they exceed it, `ro<T` to reroll once) and minimums (`minT`), and then by a keep or
+/// drop suffix (`kh`, `kl`, `dh`, `dl`) and a count (default 1), as in `2d6ro<2`, `d20min10`,
+/// `2d20kh1` or `4d6dl1`. A single die is represented without a `Times` node. In place of `dX`,
+/// a parenthesized expression is rolled as a whole, as in `2(1d6+1)`, `(2d6)r<2` or `(1d6+1)`.
+///
+/// Any of these may be followed by `>=T` to count the dice at or above T, as in `5d10>=8`.
+fn dice(i: &str) -> IResult<&str, DiceExpr> {
+    let (i, pool) = dice_pool(i)?;
+    let (i, target) = opt(preceded(tag(">="), value))(i)?;
+    Ok((i, match target {
+        None => pool,
+        Some(t) => DiceExpr::CountSuccesses(t, Arc::new(pool)),
+    }))
+}
+
+fn signed_value(i: &str) -> IResult<&str, Value> {
+    let (i, neg) = opt(char('-'))(i)?;
+    let (i, v) = value(i)?;
+    Ok((i, if neg.is_some() { -v } else { v }))
+}
+
+/// `{a,b,...}`, the faces of a custom die.
+fn faces(i: &str) -> IResult<&str, Vec<Value>> {
+    let (i, first) = preceded(char('{'), ws(signed_value))(i)?;
+    let (i, fs) = fold_many0(
+        preceded(char(','), ws(signed_value)),
+        vec![first],
+        |mut fs, v| {
+            fs.push(v);
+            fs
+        },
+    )(i)?;
+    let (i, _) = char('}')(i)?;
+    Ok((i, fs))
+}
+
+/// The `X` of `dX`, which must be at least 1.
+fn die_size(i: &str) -> IResult<&str, Die> {
+    let (rest, s) = value(i)?;
+    match Die::new(s) {
+        Ok(die) => Ok((rest, die)),
+        Err(_) => Err(nom::Err::Failure((i, INVALID_DIE))),
+    }
+}
+
+fn dice_pool(i: &str) -> IResult<&str, DiceExpr> {
+    let (i, n) = opt(count)(i)?;
+    let (i, atom) = alt((
+        preceded(one_of("dD"), alt((
+            map(one_of("fF"), |_| DiceExpr::Fudge),
+            map(char('%'), |_| DiceExpr::Percentile),
+            map(map_opt(faces, |fs| Faces::new(fs).ok()), DiceExpr::Faces),
+            map(die_size, DiceExpr::Die),
+        ))),
+        delimited(char('('), dice_expr, char(')')),
+    ))(i)?;
+    // Rerolls (`'r'`, and whether only once) and minimums (`'m'`), innermost first.
+    let (i, die) = fold_many0(
+        alt((
+            map(pair(alt((tag_no_case("ro<"), tag_no_case("r<"))), value), |(op, t)| ('r', op.len() == 3, t)),
+            map(preceded(tag_no_case("min"), value), |m| ('m', false, m)),
+        )),
+        atom,
+        |die, (op, once, v)| if op == 'r' {
+            DiceExpr::RerollBelow(v, once, Arc::new(die))
+        } else {
+            DiceExpr::Minimum(v, Arc::new(die))
+        },
+    )(i)?;
+    let (i, keep) = opt(pair(
+        alt((tag_no_case("kh"), tag_no_case("kl"), tag_no_case("dh"), tag_no_case("dl"))),
+        opt(count),
+    ))(i)?;
+    let n = n.unwrap_or(1);
+    Ok((i, match keep {
+        None => if n == 1 {
+            die
+        } else {
+            DiceExpr::Times(n, Arc::new(die))
+        },
+        Some((op, k)) => {
+            let (k, die) = (k.unwrap_or(1), Arc::new(die));
+            let op = op.as_bytes();
+            match (op[0].to_ascii_lowercase(), op[1].to_ascii_lowercase()) {
+                (b'k', b'h') => DiceExpr::KeepHighest(n, k, die),
+                (b'k', _) => DiceExpr::KeepLowest(n, k, die),
+                (_, b'h') => DiceExpr::KeepLowest(n, n.saturating_sub(k), die),
+                _ => DiceExpr::KeepHighest(n, n.saturating_sub(k), die),
+            }
+        },
+    }))
+}
+
+/// `*N` or `/N`, as the operator and constant.
+fn scale(i: &str) -> IResult<&str, (char, Value)> {
+    let (i, op) = ws(one_of("*/"))(i)?;
+    let (rest, n) = value(i)?;
+    if op == '/' && n == 0 {
+        return Err(nom::Err::Failure((i, ZERO_DIVISOR)));
+    }
+    Ok((rest, (op, n)))
+}
+
+/// A constant or dice, optionally multiplied or divided (rounding down) by constants, as in
+/// `10d6*10` or `3d6/2`.
+fn term(i: &str) -> IResult<&str, DiceExpr> {
+    let (i, first) = ws(alt((dice, map(value, DiceExpr::Const))))(i)?;
+    ws(fold_many0(
+        scale,
+        first,
+        |acc, (op, n)| if op == '*' {
+            DiceExpr::Mul(Arc::new(acc), n)
+        } else {
+            DiceExpr::DivFloor(Arc::new(acc), n)
+        },
+    ))(i)
+}
+
+/// A term preceded by `+` or `-`, negated in the latter case.
+fn signed_term(i: &str) -> IResult<&str, DiceExpr> {
+    let (i, sign) = one_of("+-")(i)?;
+    let (i, t) = term(i)?;
+    Ok((i, if sign == '-' { t.negate() } else { t }))
+}
+
+/// Dice notation, e.g. `"2d6+3"`, `"4d8 + 2d4 + 5"` or `"2d8 - 1d4"`. The first term may also be
+/// negated, as in `"-1d4+1"`.
+pub fn dice_expr(i: &str) -> IResult<&str, DiceExpr> {
+    let (i, neg) = opt(ws(char('-')))(i)?;
+    let (i, first) = term(i)?;
+    let first = if neg.is_some() { first.negate() } else { first };
+    fold_many0(
+        signed_term,
+        first,
+        |acc, t| DiceExpr::Plus(Arc::new(acc), Arc::new(t)),
+    )(i)
+}
+
+/// One clause of effect notation.
+enum Clause {
+    Kind(AttackKind),
+    Targets(usize),
+    Range(usize),
+    Hit(isize),
+    Save(Ability, usize, bool),
+    Damage(Vec<DamageRoll>, Vec<Condition>),
+    Uses(Uses),
+}
+
+fn ability(i: &str) -> IResult<&str, Ability> {
+    use Ability::*;
+    map_opt(alpha1, |s: &str| [
+        ("str", Str), ("dex", Dex), ("con", Con), ("int", Int), ("wis", Wis), ("cha", Cha),
+    ].iter().find(|(n, _)| n.eq_ignore_ascii_case(s)).map(|(_, a)| *a))(i)
+}
+
+fn damage_kind(i: &str) -> IResult<&str, DamageKind> {
+    use DamageKind::*;
+    map_opt(alpha1, |s: &str| [
+        ("acid", Acid), ("bludgeoning", Bludgeoning), ("cold", Cold), ("fire", Fire),
+        ("force", Force), ("lightning", Lightning), ("necrotic", Necrotic),
+        ("piercing", Piercing), ("poison", Poison), ("psychic", Psychic), ("radiant", Radiant),
+        ("slashing", Slashing), ("thunder", Thunder),
+    ].iter().find(|(n, _)| n.eq_ignore_ascii_case(s)).map(|(_, k)| *k))(i)
+}
+
+fn condition(i: &str) -> IResult<&str, Condition> {
+    use Condition::*;
+    map_opt(alpha1, |s: &str| [
+        ("blinded", Blinded), ("charmed", Charmed), ("deafened", Deafened),
+        ("frightened", Frightened), ("grappled", Grappled), ("incapacitated", Incapacitated),
+        ("invisible", Invisible), ("paralyzed", Paralyzed), ("petrified", Petrified),
+        ("poisoned", Poisoned), ("prone", Prone), ("restrained", Restrained),
+        ("stunned", Stunned), ("unconscious", Unconscious),
+    ].iter().find(|(n, _)| n.eq_ignore_ascii_case(s)).map(|(_, c)| *c))(i)
+}
+
+/// `<dice> <kind>`, as in `4d6 fire`.
+fn damage_roll(i: &str) -> IResult<&str, DamageRoll> {
+    let (i, ex) = ws(dice_expr)(i)?;
+    let (i, k) = ws(damage_kind)(i)?;
+    Ok((i, DamageRoll(ex, k)))
+}
+
+/// Damage rolls and conditions separated by `,` or `+`, as in `4d6 fire + prone`. Conditions
+/// are tried first, since a condition such as `deafened` would otherwise be read as a `dX` die.
+fn riders(i: &str) -> IResult<&str, (Vec<DamageRoll>, Vec<Condition>)> {
+    let rider = |i| alt((
+        map(ws(condition), |c| (None, Some(c))),
+        map(damage_roll, |r| (Some(r), None)),
+    ))(i);
+    let add = |(mut rs, mut cs): (Vec<DamageRoll>, Vec<Condition>), (r, c)| {
+        rs.extend(r);
+        cs.extend(c);
+        (rs, cs)
+    };
+    let (i, first) = rider(i)?;
+    fold_many0(preceded(one_of(",+"), rider), add((Vec::new(), Vec::new()), first), add)(i)
+}
+
+fn clause(i: &str) -> IResult<&str, Clause> {
+    let (i, _) = multispace0(i)?;
+    alt((
+        map(tag_no_case("melee"), |_| Clause::Kind(AttackKind::Melee)),
+        map(tag_no_case("ranged"), |_| Clause::Kind(AttackKind::Ranged)),
+        map(tag_no_case("special"), |_| Clause::Kind(AttackKind::Special)),
+        map(
+            preceded(pair(tag_no_case("target"), ws(char(':'))), pair(count, opt(ws(pair(
+                opt(ws(tag_no_case("nearest"))),
+                alt((tag_no_case("enemies"), tag_no_case("creatures"), tag_no_case("targets"))),
+            ))))),
+            |(n, _)| Clause::Targets(n),
+        ),
+        map(preceded(pair(tag_no_case("range"), ws(char(':'))), count), Clause::Range),
+        map(preceded(pair(tag_no_case("hit"), ws(opt(char('+')))), signed_value), Clause::Hit),
+        map(
+            pair(
+                preceded(pair(tag_no_case("save"), space0), ability),
+                pair(
+                    preceded(ws(tag_no_case("dc")), count),
+                    opt(ws(tag_no_case("half"))),
+                ),
+            ),
+            |(ab, (dc, half))| Clause::Save(ab, dc, half.is_some()),
+        ),
+        map(
+            preceded(
+                pair(
+                    alt((tag_no_case("damage"), tag_no_case("on hit"), tag_no_case("on fail"))),
+                    ws(char(':')),
+                ),
+                riders,
+            ),
+            |(rs, cs)| Clause::Damage(rs, cs),
+        ),
+        map(limited_uses, Clause::Uses),
+    ))(i)
+}
+
+/// Effect notation, a `;`-separated list of clauses describing an attack, as in
+/// `"save dex DC 15 half; damage: 8d6 fire; target: 2"`. The clauses are:
+///
+/// - `melee`, `ranged`, or `special` (the default), the kind of attack;
+/// - `target: N`, the number of targets, which may be followed by `enemies`, `creatures` or
+///   `targets`, and `nearest` before those (which chooses nothing, as targets aren't placed);
+/// - `range: N`, the range in feet;
+/// - `hit +N` (or `hit -N`), the bonus to the attack roll, which is otherwise not proficient;
+/// - `save ABILITY DC N`, a saving throw which avoids the damage, or halves it if followed by
+///   `half`;
+/// - `damage: 2d6 slashing, 1d6 fire`, the damage rolls, which may be separated by `+` and
+///   include conditions (`Attack::conditions`), as in `damage: 4d6 fire + prone`. This may also
+///   be written `on hit:` or `on fail:`, which mean the same; a save without `half` avoids all
+///   of it.
+///
+/// Later clauses override earlier ones. Uses (such as recharge) belong to the action; see
+/// `action_effect`.
+pub fn effect(i: &str) -> IResult<&str, Attack> {
+    map(verify(action_effect, |(_, u)| *u == Uses::Indefinite), |(atk, _)| atk)(i)
+}
+
+/// Effect notation (see `effect`) which may also have a clause in `uses` notation, giving the
+/// uses of the action that makes the attack, as in
+/// `"target: 3 nearest enemies; save DEX DC 15; on fail: 4d6 fire + prone; recharge 5-6"`.
+pub fn action_effect(i: &str) -> IResult<&str, (Attack, Uses)> {
+    let apply = |(mut atk, mut uses): (Attack, Uses), c| {
+        match c {
+            Clause::Kind(k) => atk.kind = k,
+            Clause::Targets(n) => atk.target = Target::Exactly(n),
+            Clause::Range(r) => atk.range = r,
+            Clause::Hit(h) => atk.to_hit_bonus = h,
+            Clause::Save(ab, dc, half) => atk.save = Some(Save(
+                SaveKind::Ability(ab),
+                SavingDC::Exactly(dc),
+                SaveEffect::ReducesDamage(if half { 0.5 } else { 0.0 }),
+            )),
+            Clause::Damage(rs, cs) => {
+                atk.dmg_rolls = rs;
+                atk.conditions = cs;
+            },
+            Clause::Uses(u) => uses = u,
+        }
+        (atk, uses)
+    };
+    let (i, first) = clause(i)?;
+    let (i, effect) = fold_many0(
+        preceded(char(';'), clause),
+        apply((Attack { kind: AttackKind::Special, ..Default::default() }, Uses::Indefinite), first),
+        apply,
+    )(i)?;
+    let (i, _) = multispace0(i)?;
+    Ok((i, effect))
+}
+
+/// Uses as written in stat blocks: `Recharge 5-6` (or with an en dash), `Recharge 6`,
+/// `Recharges after a Short or Long Rest`, `3/Day`, `2/Short Rest`, `1/Long Rest`, or
+/// `10 Charges, regains 1d6+4 at dawn`. Recharges are on a d6 unless a range says otherwise, and
+/// the least result must be a face of the die (so `Recharge 0` and `Recharge 7-6` are errors).
+/// Empty input is `Uses::Indefinite`.
+pub fn uses(i: &str) -> IResult<&str, Uses> {
+    ws(alt((limited_uses, map(space0, |_| Uses::Indefinite))))(i)
+}
+
+/// Any `uses` notation but the empty `Uses::Indefinite`.
+fn limited_uses(i: &str) -> IResult<&str, Uses> {
+    ws(alt((
+        map(tag_no_case("recharges after a short or long rest"), |_| Uses::ShortOrLongRest),
+        map_opt(
+            preceded(
+                pair(tag_no_case("recharge"), space0),
+                pair(value, opt(preceded(alt((tag("-"), tag("\u{2013}"))), value))),
+            ),
+            |(lo, hi)| match Die::new(hi.unwrap_or(6)) {
+                Ok(d) if 0 < lo && lo <= d.sides() => Some(Uses::Recharge(lo, d)),
+                _ => None,
+            },
+        ),
+        map(pair(count, preceded(char('/'), tag_no_case("day"))), |(n, _)| Uses::PerDay(n)),
+        map(pair(count, preceded(char('/'), tag_no_case("short rest"))), |(n, _)| Uses::PerShortRest(n)),
+        map(pair(count, preceded(char('/'), tag_no_case("long rest"))), |(n, _)| Uses::PerLongRest(n)),
+        map(
+            pair(
+                count,
+                delimited(
+                    pair(ws(tag_no_case("charges,")), tag_no_case("regains")),
+                    dice_expr,
+                    tag_no_case("at dawn"),
+                ),
+            ),
+            |(max, regain)| Uses::Charges { max, regain },
+        ),
+    )))(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ExpectedValue;
+    use alloc::string::ToString;
+
+    #[test]
+    fn dice_notation() {
+        let ex: DiceExpr = "2d6+3".parse().unwrap();
+        assert_eq!(ex.expected(), 10.0);
+        let ex: DiceExpr = " 4d8 + 2d4 + 5 ".parse().unwrap();
+        assert_eq!(ex.expected(), 28.0);
+        let ex: DiceExpr = "d20".parse().unwrap();
+        assert_eq!(ex.expected(), 10.5);
+        assert_eq!(
+            "2d6 + x".parse::<DiceExpr>().unwrap_err(),
+            ParseError { offset: 4, kind: ParseErrorKind::Syntax },
+        );
+        assert_eq!("1d0".parse::<DiceExpr>().unwrap_err().kind, ParseErrorKind::InvalidDie);
+        assert_eq!("1d{}".parse::<DiceExpr>().unwrap_err().kind, ParseErrorKind::Syntax);
+        assert_eq!(
+            "99999999999999999999999d6".parse::<DiceExpr>().unwrap_err().kind,
+            ParseErrorKind::Overflow,
+        );
+    }
+
+    #[test]
+    fn uses_notation() {
+        assert_eq!("Recharge 5-6".parse::<Uses>().unwrap(), Uses::Recharge(5, Die::d6()));
+        assert_eq!("Recharge 5\u{2013}6".parse::<Uses>().unwrap(), Uses::Recharge(5, Die::d6()));
+        assert_eq!("recharge 6".parse::<Uses>().unwrap(), Uses::Recharge(6, Die::d6()));
+        assert_eq!("3/Day".parse::<Uses>().unwrap(), Uses::PerDay(3));
+        assert_eq!("".parse::<Uses>().unwrap(), Uses::Indefinite);
+        for bad in &["Recharge 5-0", "Recharge 7-6", "Recharge 0", "Recharge 0-6"] {
+            assert!(bad.parse::<Uses>().is_err(), "{}", bad);
+        }
+        let staff = Uses::Charges { max: 10, regain: DiceExpr::parse("1d6+4").unwrap() };
+        assert_eq!(staff.to_string(), "10 Charges, regains 1d6+4 at dawn");
+        for u in &[
+            Uses::Recharge(5, Die::d6()), Uses::Recharge(6, Die::d6()), Uses::PerDay(1),
+            Uses::ShortOrLongRest, Uses::PerShortRest(2), Uses::PerLongRest(1), staff.clone(),
+        ] {
+            assert_eq!(&u.to_string().parse::<Uses>().unwrap(), u);
+        }
+    }
+
+    #[test]
+    fn effect_notation() {
+        let atk: Attack = "save dex DC 15 half; damage: 8d6 fire; target: 2".parse().unwrap();
+        assert!(matches!(atk.kind, AttackKind::Special));
+        assert!(matches!(atk.target, Target::Exactly(2)));
+        assert!(matches!(
+            atk.save,
+            Some(Save(SaveKind::Ability(Ability::Dex), SavingDC::Exactly(15), SaveEffect::ReducesDamage(_))),
+        ));
+        assert_eq!(atk.dmg_rolls[0].1, DamageKind::Fire);
+        let atk: Attack = "melee; hit +5; damage: 2d6+3 slashing, 1d6 poison".parse().unwrap();
+        assert_eq!((atk.to_hit_bonus, atk.dmg_rolls.len()), (5, 2));
+        assert_eq!(atk.dmg_rolls[1].0.expected(), 3.5);
+        assert_eq!("damage: 1d6 shiny".parse::<Attack>().unwrap_err().kind, ParseErrorKind::Syntax);
+        assert_eq!("save foo DC 15".parse::<Attack>().unwrap_err().kind, ParseErrorKind::Syntax);
+        assert!("damage: 1d6 fire; recharge 5-6".parse::<Attack>().is_err());
+    }
+
+    #[test]
+    fn action_notation() {
+        let act = Action::parse(
+            "Fire Breath",
+            "target: 3 nearest enemies; save DEX DC 15; on fail: 4d6 fire + prone; recharge 5-6",
+        ).unwrap();
+        assert_eq!(act.uses, Uses::Recharge(5, Die::d6()));
+        let atk = act.kind.attacks().next().unwrap();
+        assert_eq!(atk.target, Target::Exactly(3));
+        assert_eq!(
+            atk.save,
+            Some(Save(SaveKind::Ability(Ability::Dex), SavingDC::Exactly(15), SaveEffect::ReducesDamage(0.0))),
+        );
+        assert_eq!(atk.dmg_rolls, vec![DamageRoll(DiceExpr::parse("4d6").unwrap(), DamageKind::Fire)]);
+        assert_eq!(atk.conditions, vec![Condition::Prone]);
+        let act = Action::parse("Slam", "melee; hit +4; on hit: 1d8+2 bludgeoning, 1d6 cold, grappled").unwrap();
+        assert_eq!(act.uses, Uses::Indefinite);
+        let atk = act.kind.attacks().next().unwrap();
+        assert_eq!((atk.dmg_rolls.len(), atk.conditions.as_slice()), (2, &[Condition::Grappled][..]));
+        assert!(Action::parse("Gaze", "target: 3 bananas").is_err());
+        let atk: Attack = "damage: 1d6 fire + deafened, d4 thunder".parse().unwrap();
+        assert_eq!(atk.conditions, vec![Condition::Deafened]);
+        assert_eq!(atk.dmg_rolls[1], DamageRoll(DiceExpr::Die(Die::d4()), DamageKind::Thunder));
+    }
+}
@@ -1,7 +1,10 @@
-use std::f64::consts::PI;
+use crate::citation::*;
+
+use core::f64::consts::PI;
 
 /// 5e PHB, p. 204
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Area {
     Line { length: f64, width: f64 },
     Cylinder { height: f64, radius: f64 },
@@ -10,15 +13,22 @@ pub enum Area {
     Cube { length: f64 },
 }
 
+/// sqrt(3) / 2, spelled out since `f64::sqrt` isn't available without std.
+const SQRT_3_OVER_2: f64 = 0.866_025_403_784_438_6;
+
+impl Cited for Area {
+    const CITATION: Citation = Citation::phb(204);
+}
+
 impl Area {
     /// Determine approximate lateral area subtended by this figure in its default orientation.
     pub fn floor_area(&self) -> f64 {
-        match self {
-            &Area::Line { length: l, width: w } => l * w,
-            &Area::Cylinder { radius: r, .. } => PI * r * r,
-            &Area::Sphere { radius: r } => PI * r * r,
-            &Area::Cone { length: l } => 0.5 * 3.0f64.sqrt() * l * l,
-            &Area::Cube { length: l } => l * l,
+        match *self {
+            Area::Line { length: l, width: w } => l * w,
+            Area::Cylinder { radius: r, .. } => PI * r * r,
+            Area::Sphere { radius: r } => PI * r * r,
+            Area::Cone { length: l } => SQRT_3_OVER_2 * l * l,
+            Area::Cube { length: l } => l * l,
         }
     }
 }
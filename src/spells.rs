@@ -0,0 +1,195 @@
+//! Spell slot progression (5e PHB, pp. 164-165).
+
+use crate::citation::*;
+use crate::util;
+
+/// Spell slots per spell level (1st through 9th) for each caster level, 1 through 20. This is
+/// the Multiclass Spellcaster table, which is also every full caster's table (5e PHB, p. 165).
+pub const SLOTS: [[usize; 9]; 20] = [
+    [2, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 0, 0, 0, 0, 0, 0, 0, 0],
+    [4, 2, 0, 0, 0, 0, 0, 0, 0],
+    [4, 3, 0, 0, 0, 0, 0, 0, 0],
+    [4, 3, 2, 0, 0, 0, 0, 0, 0],
+    [4, 3, 3, 0, 0, 0, 0, 0, 0],
+    [4, 3, 3, 1, 0, 0, 0, 0, 0],
+    [4, 3, 3, 2, 0, 0, 0, 0, 0],
+    [4, 3, 3, 3, 1, 0, 0, 0, 0],
+    [4, 3, 3, 3, 2, 0, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 1],
+    [4, 3, 3, 3, 3, 1, 1, 1, 1],
+    [4, 3, 3, 3, 3, 2, 1, 1, 1],
+    [4, 3, 3, 3, 3, 2, 2, 1, 1],
+];
+
+/// Spell slots for a caster level; level 0 (or none) has no slots, and levels past 20 are 20.
+pub fn slots(caster_level: usize) -> [usize; 9] {
+    match caster_level {
+        0 => [0; 9],
+        l => SLOTS[l.min(20) - 1],
+    }
+}
+
+/// How a class's levels count toward caster level.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum CasterKind {
+    /// Bard, cleric, druid, sorcerer, wizard.
+    Full,
+    /// Paladin, ranger.
+    Half,
+    /// Eldritch Knight, Arcane Trickster.
+    Third,
+}
+
+impl Cited for CasterKind {
+    const CITATION: Citation = Citation::phb(164);
+}
+
+impl CasterKind {
+    /// Caster level from levels in a single class. Half and third casters gain spellcasting at
+    /// 2nd and 3rd level, respectively, and then round up.
+    pub fn caster_level(self, level: usize) -> usize {
+        match self {
+            CasterKind::Full => level,
+            CasterKind::Half if level >= 2 => level.div_ceil(2),
+            CasterKind::Third if level >= 3 => level.div_ceil(3),
+            _ => 0,
+        }
+    }
+
+    /// This class's contribution to a multiclass caster level, which rounds down (5e PHB,
+    /// p. 164).
+    pub fn multiclass_levels(self, level: usize) -> usize {
+        match self {
+            CasterKind::Full => level,
+            CasterKind::Half => level / 2,
+            CasterKind::Third => level / 3,
+        }
+    }
+}
+
+/// Caster level from levels in several spellcasting classes, as (kind, class level) pairs. A
+/// single class uses its own progression; several combine per the multiclassing rules.
+pub fn multiclass_caster_level(classes: &[(CasterKind, usize)]) -> usize {
+    match classes {
+        [(kind, level)] => kind.caster_level(*level),
+        _ => classes.iter().map(|(kind, level)| kind.multiclass_levels(*level)).sum(),
+    }
+}
+
+/// Spell slots from levels in several spellcasting classes; see `multiclass_caster_level`.
+pub fn multiclass_slots(classes: &[(CasterKind, usize)]) -> [usize; 9] {
+    slots(multiclass_caster_level(classes))
+}
+
+/// The spellcasting classes and subclasses (5e PHB, ch. 3).
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum SpellcastingClass {
+    Bard,
+    Cleric,
+    Druid,
+    Paladin,
+    Ranger,
+    Sorcerer,
+    Warlock,
+    Wizard,
+    EldritchKnight,
+    ArcaneTrickster,
+}
+
+/// Spells known at each class level, 1 through 20 (from each class's table in 5e PHB, ch. 3).
+const BARD_KNOWN: [usize; 20] = [4, 5, 6, 7, 8, 9, 10, 11, 12, 14, 15, 15, 16, 18, 19, 19, 20, 22, 22, 22];
+const RANGER_KNOWN: [usize; 20] = [0, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11];
+const SORCERER_KNOWN: [usize; 20] = [2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 12, 13, 13, 14, 14, 15, 15, 15, 15];
+const WARLOCK_KNOWN: [usize; 20] = [2, 3, 4, 5, 6, 7, 8, 9, 10, 10, 11, 11, 12, 12, 13, 13, 14, 14, 15, 15];
+const THIRD_CASTER_KNOWN: [usize; 20] = [0, 0, 3, 4, 4, 4, 5, 6, 6, 7, 8, 8, 9, 10, 10, 11, 11, 11, 12, 13];
+
+impl SpellcastingClass {
+    /// How this class's levels count toward caster level; None for warlocks, whose Pact Magic
+    /// is separate (see `pact_slots`).
+    pub fn caster_kind(self) -> Option<CasterKind> {
+        match self {
+            SpellcastingClass::Warlock => None,
+            SpellcastingClass::Paladin | SpellcastingClass::Ranger => Some(CasterKind::Half),
+            SpellcastingClass::EldritchKnight | SpellcastingClass::ArcaneTrickster => Some(CasterKind::Third),
+            _ => Some(CasterKind::Full),
+        }
+    }
+
+    /// The number of spells known at a class level, for classes which know a fixed number of
+    /// spells; None for classes which prepare spells instead.
+    pub fn spells_known(self, level: usize) -> Option<usize> {
+        let table = match self {
+            SpellcastingClass::Bard => &BARD_KNOWN,
+            SpellcastingClass::Ranger => &RANGER_KNOWN,
+            SpellcastingClass::Sorcerer => &SORCERER_KNOWN,
+            SpellcastingClass::Warlock => &WARLOCK_KNOWN,
+            SpellcastingClass::EldritchKnight | SpellcastingClass::ArcaneTrickster => &THIRD_CASTER_KNOWN,
+            _ => return None,
+        };
+        Some(match level {
+            0 => 0,
+            l => table[l.min(20) - 1],
+        })
+    }
+
+    /// The number of spells a preparing class can prepare after a long rest: its level (half its
+    /// level, rounded down, for paladins) plus its spellcasting ability modifier, minimum one
+    /// (e.g. 5e PHB, p. 58). None for classes which know spells instead, or paladins below 2nd
+    /// level, who have no spellcasting yet.
+    pub fn spells_prepared(self, level: usize, modifier: isize) -> Option<usize> {
+        let levels = match self {
+            SpellcastingClass::Cleric | SpellcastingClass::Druid | SpellcastingClass::Wizard => level,
+            SpellcastingClass::Paladin if level >= 2 => level / 2,
+            _ => return None,
+        };
+        Some(util::clamp_isize(levels as isize + modifier).max(1))
+    }
+}
+
+/// Pact Magic slots for a warlock level, as (number of slots, slot level). These are separate
+/// from, and don't combine with, other spellcasting (5e PHB, p. 107).
+pub fn pact_slots(level: usize) -> (usize, usize) {
+    match level {
+        0 => (0, 0),
+        1 => (1, 1),
+        2 => (2, 1),
+        3..=10 => (2, level.div_ceil(2)),
+        11..=16 => (3, 5),
+        _ => (4, 5),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progression() {
+        assert_eq!(slots(5)[..3], [4, 3, 2]);
+        assert_eq!(slots(CasterKind::Half.caster_level(1)), [0; 9]);
+        assert_eq!(slots(CasterKind::Half.caster_level(5))[..2], [4, 2]);
+        assert_eq!(slots(CasterKind::Third.caster_level(7))[..2], [4, 2]);
+        // Paladin 3 / Sorcerer 3 casts as a 4th-level caster.
+        assert_eq!(multiclass_caster_level(&[(CasterKind::Half, 3), (CasterKind::Full, 3)]), 4);
+        assert_eq!(pact_slots(5), (2, 3));
+        assert_eq!(pact_slots(11), (3, 5));
+    }
+
+    #[test]
+    fn spell_counts() {
+        assert_eq!(SpellcastingClass::Wizard.spells_prepared(1, 3), Some(4));
+        assert_eq!(SpellcastingClass::Cleric.spells_prepared(1, -2), Some(1));
+        assert_eq!(SpellcastingClass::Paladin.spells_prepared(5, 3), Some(5));
+        assert_eq!(SpellcastingClass::Paladin.spells_prepared(1, 3), None);
+        assert_eq!(SpellcastingClass::Sorcerer.spells_prepared(5, 3), None);
+        assert_eq!(SpellcastingClass::Sorcerer.spells_known(5), Some(6));
+        assert_eq!(SpellcastingClass::Wizard.spells_known(5), None);
+    }
+}
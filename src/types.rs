@@ -1,4 +1,16 @@
 /// Trait for types which represent something which has a distribution holding an expected value.
 pub trait ExpectedValue {
     fn expected(&self) -> f64;
+
+    /// The expected value as printed in stat blocks, which round the mean down (e.g. 2d6 is 7 and
+    /// 1d8+2 is 6).
+    fn expected_statblock(&self) -> isize {
+        crate::util::floor(self.expected())
+    }
+}
+
+/// Trait for types whose expected value depends on a context, such as attacks, whose damage
+/// depends on who makes them and against whom (see `combat::CombatPair`).
+pub trait ExpectedValueIn<C> {
+    fn expected_in(&self, ctx: &C) -> f64;
 }
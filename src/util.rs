@@ -1,4 +1,4 @@
-use std::convert::TryInto;
+use core::convert::TryInto;
 
 /// Convert a signed isize into the nearest usize (rounding negatives to zero).
 pub fn clamp_isize(i: isize) -> usize {
@@ -8,3 +8,13 @@ pub fn clamp_isize(i: isize) -> usize {
         i.try_into().unwrap()  // Shouldn't fail
     }
 }
+
+/// The greatest integer at most f (`f64::floor` requires std).
+pub fn floor(f: f64) -> isize {
+    let t = f as isize;
+    if (t as f64) > f {
+        t - 1
+    } else {
+        t
+    }
+}